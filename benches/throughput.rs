@@ -0,0 +1,83 @@
+//! Throughput benchmarks for the library.
+//!
+//! Run with `cargo bench`. Each benchmark processes a fixed input a number of times and reports
+//! the throughput in MB/s, so that regressions show up as a drop in the numbers.
+
+extern crate binutils;
+
+use std::io::{self, Write};
+use std::time::Instant;
+
+use binutils::convert::{encode_hex, decode_hex};
+use binutils::hexdump::HexDumper;
+use binutils::strings::{self, Options, StringScanner};
+
+/// The size of the input, in bytes.
+const INPUT_SIZE: usize = 16 * 1024 * 1024;
+/// The number of times each benchmark is run.
+const ROUNDS: u32 = 4;
+
+/// Generate pseudorandom input, with runs of printable text between the noise.
+fn input() -> Vec<u8> {
+    let mut state = 0x2545f4914f6cdd1du64;
+    let mut buf = Vec::with_capacity(INPUT_SIZE);
+
+    while buf.len() < INPUT_SIZE {
+        // Xorshift.
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+
+        if state & 7 == 0 {
+            buf.extend_from_slice(b"a printable string ");
+        } else {
+            buf.extend_from_slice(&state.to_le_bytes());
+        }
+    }
+
+    buf.truncate(INPUT_SIZE);
+    buf
+}
+
+/// Run `f` on the input and report its throughput.
+fn bench<F: FnMut(&[u8])>(name: &str, input: &[u8], mut f: F) {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        f(input);
+    }
+    let elapsed = start.elapsed();
+
+    let secs = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 * 1e-9;
+    let mb = (input.len() as f64 * ROUNDS as f64) / (1024.0 * 1024.0);
+    println!("{:<16} {:>10.1} MB/s", name, mb / secs);
+}
+
+fn main() {
+    let input = input();
+
+    bench("strings::read", &input, |input| {
+        strings::read(input, io::sink(), Options::default()).unwrap();
+    });
+
+    bench("StringScanner", &input, |input| {
+        for string in StringScanner::new(input, Options::default()) {
+            string.unwrap();
+        }
+    });
+
+    bench("HexDumper", &input, |input| {
+        let mut dumper = HexDumper::new(io::sink(), Default::default());
+        dumper.write_all(input).unwrap();
+        dumper.finish().unwrap();
+    });
+
+    let mut hex = vec![0; input.len() * 2];
+    bench("encode_hex", &input, |input| {
+        encode_hex(input, &mut hex);
+    });
+
+    let mut bytes = vec![0; input.len()];
+    bench("decode_hex", &hex[..input.len()], |hex| {
+        decode_hex(hex, &mut bytes).unwrap();
+    });
+}
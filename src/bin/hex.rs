@@ -2,28 +2,42 @@ extern crate binutils;
 
 use std::env;
 use std::fs;
-use std::io::{self, Stderr, Write, Read};
+use std::io::{self, BufWriter, Stderr, Write, Read};
 
 use binutils::extra::option::OptionalExt;
 use binutils::extra::io::{WriteExt, fail};
-use binutils::convert::{u8_to_hex, hex_to_u8, ascii_to_hex, hex_to_ascii};
+use binutils::Result;
+use binutils::convert::{HexDecoder, HexEncoder, HexLayout};
 
-const HELP: &'static [u8] = br#"
+const HELP: &[u8] = br#"
     NAME
         hex - read a binary file and output it in hexadecimal representation.
     SYNOPSIS
-        hex [-h | --help] [-d | --decode] [FILE]
+        hex [-h | --help] [-d | --decode] [-u | --upper] [-w COLS | --width=COLS] [-g BYTES | --group=BYTES]
+            [-s SEP | --separator=SEP] [FILE]
     DESCRIPTION
         This utility will read the file from the path given in the argument. If no argument is given, 'hex' will read from the standard input. The content of the file is then encoded/decoded in/from hexadecimal.
 
-        In opposite to GNU Hexdump, 'hex' will treat the input as big endianness left-to-right byte stream. Furthermore, there is no stylistic representation, the output is just plain ASCII, with no spaces or new-lines for seperations.
+        In opposite to GNU Hexdump, 'hex' will treat the input as big endianness left-to-right byte stream. Furthermore, there is no stylistic representation by default, the output is just plain ASCII, with no spaces or new-lines for seperations, except for a final new-line. The options below can be used to wrap and group the output.
     OPTIONS
         -h
         --help
             Print this manual page.
         -d
         --decode
-            Decode hexadecimal.
+            Decode hexadecimal. Both lowercase and uppercase digits are accepted. Whitespace, ':' and '-' separators, and '0x' or '\x' prefixes are allowed between bytes, so hex dumps, MAC addresses and fingerprints can be decoded directly. Decoding stops at the first invalid digit, which is reported along with its offset. A final digit without a pair is an error.
+        -u
+        --upper
+            Use uppercase hexadecimal digits.
+        -w COLS
+        --width=COLS
+            Wrap the output, such that no line is longer than COLS characters. Lines are only broken between bytes.
+        -g BYTES
+        --group=BYTES
+            Seperate every group of BYTES bytes by the separator.
+        -s SEP
+        --separator=SEP
+            The separator used between groups. Defaults to a single space. If no group size is given, every byte is seperated.
     AUTHOR
         This program was written by Ticki. Bugs should be reported in the Github repository, 'redox-os/binutils'.
     COPYRIGHT
@@ -36,68 +50,150 @@ const HELP: &'static [u8] = br#"
         THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 "#;
 
-fn encode<R: Read, W: Write>(stdin: R, mut stdout: W, mut stderr: Stderr) {
+/// The size of the blocks read from the input.
+const BLOCK_SIZE: usize = 64 * 1024;
+
+/// Read a block of the input, returning its length, which is zero at the end of the input.
+fn read_block<R: Read>(stdin: &mut R, block: &mut [u8]) -> io::Result<usize> {
+    loop {
+        match stdin.read(block) {
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+            res => return res,
+        }
+    }
+}
+
+fn encode<R: Read, W: Write>(mut stdin: R, mut stdout: W, layout: HexLayout) -> Result<()> {
     // Encode the input stream to hexadecimal output stream.
 
-    for i in stdin.bytes() {
-        let (a, b) = u8_to_hex(i.try(&mut stderr));
-        stdout.write(&[hex_to_ascii(a), hex_to_ascii(b)]).try(&mut stderr);
+    let mut encoder = HexEncoder::new(layout);
+    let mut block = vec![0; BLOCK_SIZE];
+    let mut out = Vec::with_capacity(BLOCK_SIZE * 3);
+
+    loop {
+        let n = read_block(&mut stdin, &mut block)?;
+        if n == 0 {
+            break;
+        }
+
+        out.clear();
+        encoder.encode(&block[..n], &mut out);
+        stdout.write_all(&out)?;
     }
+
+    out.clear();
+    encoder.finish(&mut out);
+    stdout.write_all(&out)?;
+    stdout.flush()?;
+
+    Ok(())
 }
 
-fn decode<R: Read, W: Write>(stdin: R, mut stdout: W, mut stderr: Stderr) {
+fn decode<R: Read, W: Write>(mut stdin: R, mut stdout: W) -> Result<()> {
     // Decode hexadecimal to base-256, raw byte stream.
 
-    let mut iter = stdin.bytes();
+    let mut decoder = HexDecoder::new();
+    let mut block = vec![0; BLOCK_SIZE];
+    let mut out = Vec::with_capacity(BLOCK_SIZE / 2);
+
     loop {
-        let i = if let Some(x) = iter.next() {
-            x.try(&mut stderr)
-        } else {
-            break
-        };
-        let j = if let Some(x) = iter.next() {
-            x.try(&mut stderr)
-        } else {
-            break
-        };
-
-        stdout.write(&[hex_to_u8((ascii_to_hex(i), ascii_to_hex(j)))]).try(&mut stderr);
+        let n = read_block(&mut stdin, &mut block)?;
+        if n == 0 {
+            break;
+        }
+
+        out.clear();
+        let res = decoder.decode(&block[..n], &mut out);
+        // The bytes preceding an error are still written.
+        stdout.write_all(&out)?;
+        res?;
+    }
+
+    stdout.flush()?;
+    decoder.finish()
+}
+
+/// Parse a positive number given to an option.
+fn parse_num(arg: Option<&str>, what: &str, stderr: &mut Stderr) -> usize {
+    match arg.and_then(|n| n.parse().ok()) {
+        Some(0) | None => fail(&format!("invalid {}.", what), stderr),
+        Some(n) => n,
     }
 }
 
 fn main() {
     let stdout = io::stdout();
-    let mut stdout = stdout.lock();
+    let mut stdout = BufWriter::new(stdout.lock());
     let mut stderr = io::stderr();
 
-    let mut args = env::args();
-    if args.len() > 2 {
-        fail("too many arguments.", &mut stderr);
-    }
+    let mut decoding = false;
+    let mut layout = HexLayout::default();
+    let mut separator = false;
+    let mut path = None;
 
-    match args.nth(1) {
-        None => encode(io::stdin(), stdout, stderr),
-        Some(a) => match a.as_ref() { // MIR plz
+    let mut args = env::args().skip(1);
+    while let Some(a) = args.next() {
+        match a.as_ref() { // MIR plz
             "-h" | "--help" => {
                 stdout.writeln(HELP).try(&mut stderr);
+                return;
             },
-            "-d" | "--decode" => {
-                match args.next() {
-                    Some(f) => {
-                        let file = fs::File::open(f).try(&mut stderr);
-                        decode(file, stdout, stderr);
-                    },
-                    None => {
-                        let stdin = io::stdin();
-                        decode(stdin.lock(), stdout, stderr);
-                    },
-                }
+            "-d" | "--decode" => decoding = true,
+            "-u" | "--upper" => layout.upper = true,
+            "-w" | "--width" => {
+                let n = args.next();
+                let n = n.as_ref().map(|n| n.as_ref());
+                layout.width = Some(parse_num(n, "width", &mut stderr));
+            },
+            "-g" | "--group" => {
+                let n = args.next();
+                let n = n.as_ref().map(|n| n.as_ref());
+                layout.group = Some(parse_num(n, "group size", &mut stderr));
+            },
+            "-s" | "--separator" => {
+                layout.separator = args.next().fail("missing separator.", &mut stderr);
+                separator = true;
+            },
+            a if a.starts_with("--width=") => {
+                layout.width = Some(parse_num(Some(&a[8..]), "width", &mut stderr));
+            },
+            a if a.starts_with("--group=") => {
+                layout.group = Some(parse_num(Some(&a[8..]), "group size", &mut stderr));
+            },
+            a if a.starts_with("--separator=") => {
+                layout.separator = a[12..].to_owned();
+                separator = true;
             },
             // Read from file instead of standard input.
             f => {
-                let file = fs::File::open(f).try(&mut stderr);
-                encode(file, stdout, stderr);
+                if path.is_some() {
+                    fail("too many arguments.", &mut stderr);
+                }
+                path = Some(f.to_owned());
             },
+        }
+    }
+
+    if separator && layout.group.is_none() {
+        layout.group = Some(1);
+    }
+
+    match (path, decoding) {
+        (Some(f), true) => {
+            let file = fs::File::open(f).try(&mut stderr);
+            decode(file, stdout).try(&mut stderr);
+        },
+        (None, true) => {
+            let stdin = io::stdin();
+            decode(stdin.lock(), stdout).try(&mut stderr);
+        },
+        (Some(f), false) => {
+            let file = fs::File::open(f).try(&mut stderr);
+            encode(file, stdout, layout).try(&mut stderr);
+        },
+        (None, false) => {
+            let stdin = io::stdin();
+            encode(stdin.lock(), stdout, layout).try(&mut stderr);
         },
     }
 }
@@ -1,24 +1,32 @@
 extern crate binutils;
 
 use std::env;
-use std::fs;
-use std::io::{self, Write, Read, Stderr};
-use std::mem;
+use std::fs::{self, OpenOptions};
+use std::path::Path;
+use std::str;
+use std::io::{self, BufWriter, Write, Read, Stderr};
 
 use binutils::extra::option::OptionalExt;
 use binutils::extra::io::{WriteExt, fail};
-use binutils::convert::{u8_to_hex, hex_to_u8, u32_byte_array, hex_to_ascii, ascii_to_hex};
-use binutils::strings::IsPrintable;
+use binutils::Result;
+use binutils::format::{self, Format};
+use binutils::hexdump::{self, Forward, HexDumper, Language, Layout, Skip};
 
-const HELP: &'static [u8] = br#"
+const HELP: &[u8] = br#"
     NAME
         hexdump - dump the hexidecimal representation of a byte stream.
     SYNOPSIS
-        hexdump [-h | --help] [-r | --reverse] [FILE]
+        hexdump [-h | --help] [-r | --reverse] [-s [-]OFFSET | --seek=[-]OFFSET] [-l LEN | --len=LEN]
+                [-c COLS | --cols=COLS] [-g BYTES | --group=BYTES] [-E | --little-endian] [-b | --binary]
+                [-A WIDTH | --address-width=WIDTH] [-o OFFSET | --displacement=OFFSET] [-v | --no-squeezing]
+                [-i | --include[=LANG]] [-n NAME | --name=NAME] [-p | --plain]
+                [-e FORMAT | --format=FORMAT]... [-f FILE | --format-file=FILE]... [-C | --canonical] [FILE [OUTFILE]]
     DESCRIPTION
         This utility will dump the hexidecimal representation of a file or the standard input, in a stylized way. Hexdump utility behaves like 'xxd'.
 
-        The first column signifies the address of the first byte on the line. Each line contains 16 bytes, grouped in groups of two bytes, sepereated by space. The last column contains the printable characters in the last 16 bytes. The non-printable characters are replaced by a '.'.
+        The first column signifies the address of the first byte on the line. Addresses are 64-bit, and the column grows beyond its 8 digits when needed. Each line contains 16 bytes (or COLS bytes), grouped in groups of two bytes (or BYTES bytes), sepereated by space. The last column contains the printable characters of the line. The non-printable characters are replaced by a '.'.
+
+        Runs of identical lines are replaced by a single line containing '*', like BSD hexdump and 'od'. The last line of the dump is always shown, so the length of the run can be determined from the addresses.
     OPTIONS
         -h
         --help
@@ -26,6 +34,66 @@ const HELP: &'static [u8] = br#"
         -r
         --reverse
             Do the reverse dump (consume the dump and output the bytes it defines). This is useful for usage within editors.
+
+            The address of every line is parsed, and its bytes are written at that address (less the displacement given by '-o'). Lines may hold any number of groups of hexadecimal, or binary, digits, and the ASCII column is recognized by the two spaces preceding it. A '*' line is expanded to repetitions of the line before it, up to the address of the line after it, so a '*' can not be the first line. Malformed lines are reported along with their line number.
+
+            If OUTFILE is given, the bytes are written to it without truncating it, such that a partial dump patches the file. Otherwise gaps between the lines are filled with zeros, and the addresses may not go backwards. A FILE of '-' reads the dump from the standard input. The reverse dump can not be limited by '-s' or '-l'.
+        -s [-]OFFSET
+        --seek=[-]OFFSET
+            Start dumping at OFFSET bytes into the input. A negative OFFSET is relative to the end of the file, and can not be used with the standard input. The address column starts at the offset. Files are seeked, whereas the skipped part of the standard input is read and discarded.
+        -l LEN
+        --len=LEN
+            Stop dumping after LEN bytes.
+        -c COLS
+        --cols=COLS
+            Put COLS bytes on each line. Defaults to 16.
+        -g BYTES
+        --group=BYTES
+            Group the bytes of each line in groups of BYTES bytes. Defaults to 2, 4 in little-endian mode or 1 in binary mode. A group size of 0 disables grouping.
+        -E
+        --little-endian
+            Show each group as a little-endian integer, that is, with its bytes in reverse order. The reverse dump of a little-endian dump must also be given '-E' and the group size.
+        -b
+        --binary
+            Show every byte as eight binary digits, the most significant bit first, instead of two hexadecimal digits, like 'xxd -b'. There are 6 bytes on each line, unless COLS is given, and the bytes are grouped one by one, unless BYTES is given. The reverse dump of a binary dump must also be given '-b'.
+
+        -i
+        --include[=LANG]
+            Output the bytes as an array definition, for including them in source code, like 'xxd -i'. LANG is 'c' (the default) or 'rust'. C gives 'unsigned char NAME[] = {...};' followed by 'unsigned int NAME_len = N;', whereas Rust gives 'pub static NAME: [u8; N] = [...];'. There are 12 bytes on each line, unless COLS is given.
+
+            The identifier is derived from the file name, by replacing the characters, which can not be part of an identifier, by '_'. Rust identifiers are uppercased. When reading the standard input without a name, only the bytes of the array are written.
+        -n NAME
+        --name=NAME
+            Use NAME as the identifier of the array, instead of deriving it from the file name.
+        -p
+        --plain
+            Output a plain dump, that is, continuous hexadecimal without addresses or the ASCII column, like 'xxd -p'. There are 30 bytes (60 digits) on each line, unless COLS is given.
+
+            The reverse of a plain dump ignores whitespace, including line breaks, anywhere in the input, and any other non-hexadecimal character is reported along with its offset.
+        -e FORMAT
+        --format=FORMAT
+            Show the bytes in the format FORMAT, like BSD hexdump. This can be given multiple times, and every format shows the same bytes, which are processed in blocks of the size of the largest format. A format can consume at most 1048576 bytes, and widths and precisions can be at most as large.
+
+            A format is a sequence of format units, 'ITERATIONS/BYTES "STRING"', where both counts are optional. The format string STRING is applied ITERATIONS times, each time consuming BYTES bytes. It holds 'printf' style conversions, where 'd', 'i', 'u', 'o', 'x' and 'X' show integers of 1, 2, 4 (the default) or 8 bytes, 'e', 'f' and 'g' show floating point numbers of 4 or 8 (the default) bytes, 'c' shows a byte, and 's' shows a string of the bytes given by the precision or the byte count. Furthermore, '_a' and '_A', followed by 'd', 'o' or 'x', show the address of the next byte and the address of the end of the input, and '_c', '_p' and '_u' show a byte as a C escape or octal, as a printable character or '.', and as a named ASCII character.
+
+            The last format unit is repeated to fill the block, unless it has an iteration count. On the last iteration of a format unit, a trailing whitespace character is left out. Values of multiple bytes are read in the native byte order.
+        -f FILE
+        --format-file=FILE
+            Read formats from FILE, one on each line. Empty lines and lines starting with '#' are ignored.
+        -C
+        --canonical
+            Show the canonical hex+ASCII display, like 'hexdump -C'.
+        -v
+        --no-squeezing
+            Show every line, instead of replacing runs of identical lines by '*'.
+        -A WIDTH
+        --address-width=WIDTH
+            Show at least WIDTH digits in the address column, instead of 8. At most 16 digits can be given.
+        -o OFFSET
+        --displacement=OFFSET
+            Add OFFSET to the address of every line. This is useful for labelling memory dumps with their base address.
+
+        OFFSET, LEN, COLS, BYTES and WIDTH are decimal, or hexadecimal when prefixed by '0x'.
     AUTHOR
         This program was written by Ticki. Bugs should be reported in the Github repository, 'redox-os/binutils'.
     COPYRIGHT
@@ -40,166 +108,298 @@ const HELP: &'static [u8] = br#"
         THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 "#;
 
-/// Encode a single byte to the output stream
-fn encode_byte<R: Read, W: Write>(stdin: &mut R, stdout: &mut W, stderr: &mut Stderr) -> Option<u8> {
-    let byte = if let Some(x) = stdin.bytes().next() {
-        x.try(&mut *stderr)
-    } else {
-        return None;
-    };
-
-    // Convert the raw byte to hexadecimal
-    let hex = u8_to_hex(byte);
+/// The kind of output.
+enum Mode {
+    /// The stylized dump.
+    Dump,
+    /// An array definition in the given language, with the given identifier.
+    Include(Language, Option<String>),
+    /// Continuous hexadecimal in lines of fixed width.
+    Plain,
+    /// The given BSD hexdump formats.
+    Format(Vec<Format>),
+}
 
-    // Write it to stdout.
-    stdout.write(&[hex_to_ascii(hex.0), hex_to_ascii(hex.1)]).try(stderr);
+/// Dump the stream, labelling the first byte with the address `start`.
+fn encode<R: Read, W: Write>(mut stdin: R, mut stdout: W, start: u64, layout: Layout, mode: &Mode)
+                             -> Result<()> {
+    match *mode {
+        Mode::Dump => {
+            let mut dumper = HexDumper::with_address(stdout, layout, start);
+            io::copy(&mut stdin, &mut dumper)?;
+            dumper.finish()?;
+        },
+        Mode::Include(language, ref name) => {
+            // The length of a Rust array is part of its type, so the whole input is needed.
+            let mut bytes = Vec::new();
+            stdin.read_to_end(&mut bytes)?;
+            hexdump::write_include(&mut stdout, &bytes, name.as_ref().map(|n| &n[..]), language,
+                                   layout.cols)?;
+            stdout.flush()?;
+        },
+        Mode::Plain => hexdump::write_plain(stdin, stdout, layout.cols)?,
+        Mode::Format(ref formats) => {
+            let start = start.wrapping_add(layout.displacement);
+            let mut dumper = format::Dumper::with_address(stdout, formats.clone(), start);
+            dumper.set_squeeze(layout.squeeze);
+            io::copy(&mut stdin, &mut dumper)?;
+            dumper.finish()?;
+        },
+    }
 
-    Some(if byte.is_printable() {
-        byte
-    } else {
-        // If it is non-printable, write `.` to char-buffer instead.
-        b'.'
-    })
+    Ok(())
 }
 
-fn encode<R: Read, W: Write>(mut stdin: R, mut stdout: W, mut stderr: Stderr) {
-    let rem;
-    // This is the char buffer. The last 16 byte column, which prints the printable characters.
-    // The non-printable ones are replaced with `.`.
-    let mut ascii: [u8; 16] = unsafe { mem::uninitialized() };
-
-    let mut line = 0;
-
-    'a: loop {
-        // Iterate over the bytes in the line number times 16.
-        for &b in u32_byte_array(line * 16).iter() {
-            let hex = u8_to_hex(b);
-            // Print this value to the first column (denoting the address of the first byte of the
-            // line.)
-            stdout.write(&[hex_to_ascii(hex.0), hex_to_ascii(hex.1)]).try(&mut stderr);
-        }
-        stdout.write(b": ").try(&mut stderr);
-
-        // Now, we go over the actual data, printing it in hexadecimal.
-        for n in 0..8 {
-            // We add the char to the char buffer, and print it in two hex digits.
-            ascii[n * 2] = if let Some(x) = encode_byte(&mut stdin, &mut stdout, &mut stderr) {
-                x
-            } else {
-                // The end of the file is reached, set the remainder, which will later be used for
-                // alignment of the last column.
-                rem = n;
-                break 'a;
-            };
-            ascii[n * 2 + 1] = if let Some(x) = encode_byte(&mut stdin, &mut stdout, &mut stderr) {
-                x
-            } else {
-                rem = n;
-                break 'a;
-            };
-            // Seperate every two hex digits by a space.
-            stdout.write(b" ").try(&mut stderr);
-        }
-
-        stdout.write(b" ").try(&mut stderr);
-        // Print the ASCII buffer at the end of the line.
-        stdout.writeln(&ascii).try(&mut stderr);
+/// Parse a decimal or '0x' prefixed hexadecimal number.
+fn parse_num(arg: &str, stderr: &mut Stderr) -> u64 {
+    let n = if arg.starts_with("0x") || arg.starts_with("0X") {
+        u64::from_str_radix(&arg[2..], 16)
+    } else {
+        arg.parse()
+    };
 
-        // Increment the line number.
-        line += 1;
-    }
+    n.fail("invalid number.", stderr)
+}
 
-    if rem != 0 {
-        // We now align the last column using the remainder set before.
-        for _ in 0..41 - rem * 5 {
-            stdout.write(b" ").try(&mut stderr);
-        }
-        stdout.write(&ascii[..rem * 2]).try(&mut stderr);
+/// Parse the language given to '--include'.
+fn parse_language(arg: &str, stderr: &mut Stderr) -> Language {
+    match arg {
+        "c" => Language::C,
+        "rust" => Language::Rust,
+        _ => fail("unknown language, expected 'c' or 'rust'.", stderr),
     }
-
-    stdout.write(b"\n").try(&mut stderr);
 }
 
-fn decode<R: Read, W: Write>(stdin: R, mut stdout: W, mut stderr: Stderr) {
-    let mut stdin = stdin.bytes().filter(|x| x.as_ref().ok() != Some(&b' '));
-
-    loop {
-        // Skip the first column
-        stdin.nth(8);
-        // Process the inner 8 columns
-        for _ in 0..16 {
-            // The first hex digit to decode.
-            let h1 = ascii_to_hex(
-                if let Some(x) = stdin.next() {
-                    x.try(&mut stderr)
-                } else {
-                    return;
-                }
-            );
-            // The second hex digit to decode.
-            let h2 = ascii_to_hex(
-                if let Some(x) = stdin.next() {
-                    x.try(&mut stderr)
-                } else {
-                    return;
-                }
-            );
+/// Parse the format given to '-e'.
+fn parse_format(arg: Option<&str>, stderr: &mut Stderr) -> Format {
+    Format::parse(arg.fail("missing format.", stderr)).try(stderr)
+}
 
-            // Write the decoded, joined hex digits in binary form.
-            stdout.write(&[hex_to_u8((h1, h2))]).try(&mut stderr);
-        }
+/// Read the formats of the file given to '-f'.
+fn read_formats(path: Option<&str>, stderr: &mut Stderr) -> Vec<Format> {
+    let file = fs::read_to_string(path.fail("missing format file.", stderr)).try(stderr);
+    Format::parse_file(&file).try(stderr)
+}
 
-        // Skip the rest until newline.
-        loop {
-            if let Some(x) = stdin.next() {
-                if x.try(&mut stderr) == b'\n' {
-                    break;
-                }
-            } else {
-                return;
-            }
-        }
+/// Parse the offset given to '-s'.
+fn parse_skip(arg: Option<&str>, stderr: &mut Stderr) -> Skip {
+    match arg {
+        Some(a) if a.starts_with('-') => Skip::End(parse_num(&a[1..], stderr)),
+        Some(a) if a.starts_with('+') => Skip::Start(parse_num(&a[1..], stderr)),
+        Some(a) => Skip::Start(parse_num(a, stderr)),
+        None => fail("missing offset.", stderr),
     }
 }
 
 fn main() {
     let stdout = io::stdout();
-    let mut stdout = stdout.lock();
+    let mut stdout = BufWriter::new(stdout.lock());
     let mut stderr = io::stderr();
 
-    let mut args = env::args();
+    let mut reverse = false;
+    let mut skip = None;
+    let mut len = None;
+    let mut layout = Layout::default();
+    let mut cols = None;
+    let mut group = None;
+    let mut include = None;
+    let mut plain = false;
+    let mut name = None;
+    let mut formats = Vec::new();
+    let mut path = None;
+    let mut out_path = None;
 
-    // Arguments should be <= 2
-    if args.len() > 2 {
-        fail("too many arguments.", &mut stderr);
-    }
-
-    match args.nth(1) {
-        None => encode(io::stdin(), stdout, stderr),
-        Some(a) => match a.as_ref() { // MIR plz
+    let mut args = env::args().skip(1);
+    while let Some(a) = args.next() {
+        match a.as_ref() { // MIR plz
             "-h" | "--help" => {
                 // HEEEEEELP.
                 stdout.writeln(HELP).try(&mut stderr);
+                return;
             },
-            "-r" | "--reverse" => {
-                match args.next() {
-                    // Decode.
-                    None => {
-                        let stdin = io::stdin();
-                        decode(stdin.lock(), stdout, stderr);
-                    }
-                    // Encode.
-                    Some(f) => {
-                        let file = fs::File::open(f).try(&mut stderr);
-                        decode(file, stdout, stderr);
-                    }
-                }
+            "-r" | "--reverse" => reverse = true,
+            "-s" | "--seek" => {
+                let o = args.next();
+                skip = Some(parse_skip(o.as_ref().map(|o| o.as_ref()), &mut stderr));
+            },
+            "-l" | "--len" => {
+                let l = args.next().fail("missing length.", &mut stderr);
+                len = Some(parse_num(&l, &mut stderr));
+            },
+            "-E" | "--little-endian" => layout.little_endian = true,
+            "-b" | "--binary" => layout.binary = true,
+            "-v" | "--no-squeezing" => layout.squeeze = false,
+            "-i" | "--include" => include = Some(Language::C),
+            "-p" | "--plain" => plain = true,
+            "-n" | "--name" => name = Some(args.next().fail("missing name.", &mut stderr)),
+            "-e" | "--format" => {
+                let f = args.next();
+                formats.push(parse_format(f.as_ref().map(|f| f.as_ref()), &mut stderr));
+            },
+            "-f" | "--format-file" => {
+                let f = args.next();
+                formats.extend(read_formats(f.as_ref().map(|f| f.as_ref()), &mut stderr));
+            },
+            "-C" | "--canonical" => formats.extend(Format::canonical()),
+            "-A" | "--address-width" => {
+                let w = args.next().fail("missing address width.", &mut stderr);
+                layout.address_width = parse_num(&w, &mut stderr) as usize;
+            },
+            "-o" | "--displacement" => {
+                let o = args.next().fail("missing displacement.", &mut stderr);
+                layout.displacement = parse_num(&o, &mut stderr);
+            },
+            "-c" | "--cols" => {
+                let c = args.next().fail("missing column count.", &mut stderr);
+                cols = Some(parse_num(&c, &mut stderr) as usize);
+            },
+            "-g" | "--group" => {
+                let g = args.next().fail("missing group size.", &mut stderr);
+                group = Some(parse_num(&g, &mut stderr) as usize);
+            },
+            a if a.starts_with("--address-width=") => {
+                layout.address_width = parse_num(&a[16..], &mut stderr) as usize;
+            },
+            a if a.starts_with("--displacement=") => {
+                layout.displacement = parse_num(&a[15..], &mut stderr);
+            },
+            a if a.starts_with("--cols=") => {
+                cols = Some(parse_num(&a[7..], &mut stderr) as usize);
             },
+            a if a.starts_with("--group=") => {
+                group = Some(parse_num(&a[8..], &mut stderr) as usize);
+            },
+            a if a.starts_with("--include=") => {
+                include = Some(parse_language(&a[10..], &mut stderr));
+            },
+            a if a.starts_with("--name=") => name = Some(a[7..].to_owned()),
+            a if a.starts_with("--format=") => {
+                formats.push(parse_format(Some(&a[9..]), &mut stderr));
+            },
+            a if a.starts_with("--format-file=") => {
+                formats.extend(read_formats(Some(&a[14..]), &mut stderr));
+            },
+            a if a.starts_with("--seek=") => skip = Some(parse_skip(Some(&a[7..]), &mut stderr)),
+            a if a.starts_with("--len=") => len = Some(parse_num(&a[6..], &mut stderr)),
             // Read from a file, instead of standard input.
             f => {
-                let file = fs::File::open(f).try(&mut stderr);
-                encode(file, stdout, stderr);
+                if path.is_none() {
+                    path = Some(f.to_owned());
+                } else if out_path.is_none() {
+                    out_path = Some(f.to_owned());
+                } else {
+                    fail("too many arguments.", &mut stderr);
+                }
             },
+        }
+    }
+
+    if layout.address_width > 16 {
+        fail("the address width can be at most 16 digits.", &mut stderr);
+    }
+    // Arrays have 12 bytes on each line by default, plain dumps 30 bytes and binary dumps 6
+    // bytes, like 'xxd'.
+    layout.cols = cols.unwrap_or(if include.is_some() {
+        12
+    } else if plain {
+        30
+    } else if layout.binary {
+        6
+    } else {
+        16
+    });
+    if layout.cols == 0 {
+        fail("the column count must be positive.", &mut stderr);
+    }
+    // Little-endian dumps group words of 4 bytes by default, and binary dumps single bytes.
+    layout.group = group.unwrap_or(if layout.little_endian {
+        4
+    } else if layout.binary {
+        1
+    } else {
+        2
+    });
+
+    if reverse && (skip.is_some() || len.is_some()) {
+        fail("'-s' and '-l' can not be combined with '-r'.", &mut stderr);
+    }
+    let skip = skip.unwrap_or(Skip::Start(0));
+    let len = len.unwrap_or(u64::MAX);
+
+    if out_path.is_some() && !reverse {
+        fail("an output file can only be given with the reverse dump.", &mut stderr);
+    }
+    if include.is_some() && reverse {
+        fail("array definitions can not be reversed.", &mut stderr);
+    }
+    if include.is_some() && plain {
+        fail("only one of '-i' and '-p' can be given.", &mut stderr);
+    }
+    if layout.binary && (include.is_some() || plain || !formats.is_empty()) {
+        fail("binary dumps can not be combined with '-i', '-p' or formats.", &mut stderr);
+    }
+    if !formats.is_empty() && (include.is_some() || plain || reverse) {
+        fail("formats can not be combined with '-i', '-p' or '-r'.", &mut stderr);
+    }
+    if !formats.is_empty() && formats.iter().all(|f| f.bytes() == 0) {
+        fail("the formats must consume input.", &mut stderr);
+    }
+
+    let mode = match include {
+        Some(language) => {
+            // Derive the identifier from the file name, unless it is given.
+            let name = name.or_else(|| {
+                path.as_ref()
+                    .and_then(|p| Path::new(p).file_name())
+                    .map(|f| f.to_string_lossy().into_owned())
+            });
+            Mode::Include(language, name.map(|n| hexdump::identifier(&n, language)))
+        },
+        None if plain => Mode::Plain,
+        None if !formats.is_empty() => Mode::Format(formats),
+        None => Mode::Dump,
+    };
+
+    match (path, reverse) {
+        (path, true) => {
+            let stdin = io::stdin();
+            let input: Box<dyn Read> = match path {
+                Some(ref f) if f != "-" => Box::new(fs::File::open(f).try(&mut stderr)),
+                _ => Box::new(stdin.lock()),
+            };
+
+            match out_path {
+                // The file is patched, rather than truncated.
+                Some(f) => {
+                    let file = OpenOptions::new()
+                        .write(true)
+                        .create(true)
+                        .truncate(false)
+                        .open(f)
+                        .try(&mut stderr);
+                    if plain {
+                        hexdump::reverse_plain(input, BufWriter::new(file))
+                    } else {
+                        hexdump::reverse(input, BufWriter::new(file), &layout)
+                    }
+                },
+                None if plain => hexdump::reverse_plain(input, stdout),
+                None => hexdump::reverse(input, Forward::new(stdout), &layout),
+            }.try(&mut stderr);
+        },
+        (Some(f), false) => {
+            let mut file = fs::File::open(f).try(&mut stderr);
+            let start = skip.seek(&mut file).try(&mut stderr);
+
+            encode(file.take(len), stdout, start, layout, &mode).try(&mut stderr);
+        },
+        (None, false) => {
+            let stdin = io::stdin();
+            let mut stdin = stdin.lock();
+            // The standard input can not be seeked, so the bytes are skipped by reading them.
+            let start = skip.read(&mut stdin).try(&mut stderr);
+
+            encode(stdin.take(len), stdout, start, layout, &mode).try(&mut stderr);
         },
     }
 }
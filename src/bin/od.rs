@@ -0,0 +1,228 @@
+extern crate binutils;
+
+use std::env;
+use std::fs;
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Stderr};
+
+use binutils::extra::option::OptionalExt;
+use binutils::extra::io::{WriteExt, fail};
+use binutils::od::{Dumper, Options, Type};
+use binutils::strings::Radix;
+
+const HELP: &[u8] = br#"
+    NAME
+        od - dump files in octal and other formats.
+    SYNOPSIS
+        od [-h | --help] [-A RADIX | --address-radix=RADIX] [-j BYTES | --skip-bytes=BYTES] [-N BYTES | --read-bytes=BYTES]
+           [-t TYPE | --format=TYPE]... [-b] [-c] [-d] [-o] [-s] [-x] [-v | --output-duplicates] [-w BYTES | --width=BYTES]
+           [FILE]...
+    DESCRIPTION
+        This utility will dump the files given in the arguments, one after another, in the formats given by the '-t' options. If no file is given, or a file is '-', 'od' will read from the standard input.
+
+        Each line starts with the address of its first byte, followed by the values of the bytes of the line in the first format. Every other format gets a line of its own, and the values are aligned across the formats. Runs of identical lines are replaced by a single line containing '*'. The last line holds the address of the end of the input.
+
+        Values of multiple bytes are read in the native byte order. This is a clone of POSIX 'od', though it lacks the traditional offset arguments.
+    OPTIONS
+        -h
+        --help
+            Print this manual page.
+        -A RADIX
+        --address-radix=RADIX
+            Show the addresses in RADIX, 'd' for decimal, 'o' for octal (the default), 'x' for hexadecimal or 'n' for no addresses.
+        -j BYTES
+        --skip-bytes=BYTES
+            Skip BYTES bytes of the input before dumping. The addresses still count from the start of the input.
+        -N BYTES
+        --read-bytes=BYTES
+            Dump at most BYTES bytes.
+        -t TYPE
+        --format=TYPE
+            Show the bytes in the formats given by the type string TYPE. This can be given multiple times, and each type string can hold multiple type specifiers. The type specifiers are:
+
+            a       Named characters, such as 'nul' and 'sp'. The high bit of the bytes is ignored.
+            c       Characters. Non-printable characters are shown as C escapes, such as '\n', or in octal.
+            d[SIZE] Signed decimal integers.
+            f[SIZE] Floating point numbers.
+            o[SIZE] Octal integers.
+            u[SIZE] Unsigned decimal integers.
+            x[SIZE] Hexadecimal integers.
+
+            The SIZE of an integer is 1, 2, 4 or 8 bytes, or 'C', 'S', 'I' or 'L' for the sizes of 'char', 'short', 'int' and 'long'. It defaults to the size of 'int'. The SIZE of a floating point number is 4 or 8 bytes, or 'F' or 'D' for the sizes of 'float' and 'double'. It defaults to the size of 'double'. Without any type, 'o2' is used.
+        -b
+            Like '-t o1'.
+        -c
+            Like '-t c'.
+        -d
+            Like '-t u2'.
+        -o
+            Like '-t o2'.
+        -s
+            Like '-t d2'.
+        -x
+            Like '-t x2'.
+        -v
+        --output-duplicates
+            Show every line, instead of replacing runs of identical lines by '*'.
+        -w BYTES
+        --width=BYTES
+            Put BYTES bytes on each line, instead of 16. This must be a multiple of the sizes of the types.
+
+        BYTES is decimal, hexadecimal when prefixed by '0x', or octal when prefixed by '0'. It may be followed by 'b', 'k' or 'm', multiplying it by 512, 1024 or 1048576 respectively.
+    AUTHOR
+        This program was written by Ticki. Bugs should be reported in the Github repository, 'redox-os/binutils'.
+    COPYRIGHT
+        Copyright (c) 2016 Ticki
+
+        Permission is hereby granted, free of charge, to any person obtaining a copy of this software and associated documentation files (the "Software"), to deal in the Software without restriction, including without limitation the rights to use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of the Software, and to permit persons to whom the Software is furnished to do so, subject to the following conditions:
+
+        The above copyright notice and this permission notice shall be included in all copies or substantial portions of the Software.
+
+        THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+"#;
+
+/// The error for a '-j' beyond the end of the input, which is the same for files and streams.
+const SKIP_PAST_END: &str = "cannot skip past end of combined input.";
+
+/// Parse a byte count of '-j', '-N' or '-w', which may have a multiplier suffix.
+fn parse_bytes(arg: &str, stderr: &mut Stderr) -> u64 {
+    let (arg, multiplier) = match arg.as_bytes().last() {
+        Some(&b'b') if !arg.starts_with("0x") => (&arg[..arg.len() - 1], 512),
+        Some(&b'k') => (&arg[..arg.len() - 1], 1024),
+        Some(&b'm') => (&arg[..arg.len() - 1], 1024 * 1024),
+        _ => (arg, 1),
+    };
+
+    let n = if arg.starts_with("0x") || arg.starts_with("0X") {
+        u64::from_str_radix(&arg[2..], 16)
+    } else if arg.len() > 1 && arg.starts_with('0') {
+        u64::from_str_radix(&arg[1..], 8)
+    } else {
+        arg.parse()
+    };
+
+    n.ok().and_then(|n| n.checked_mul(multiplier)).fail("invalid byte count.", stderr)
+}
+
+/// Parse the address radix given to '-A'.
+fn parse_radix(arg: Option<&str>, stderr: &mut Stderr) -> Option<Radix> {
+    match arg {
+        Some("d") => Some(Radix::Decimal),
+        Some("o") => Some(Radix::Octal),
+        Some("x") => Some(Radix::Hexadecimal),
+        Some("n") => None,
+        _ => fail("invalid address radix, expected 'd', 'o', 'x' or 'n'.", stderr),
+    }
+}
+
+/// Parse the type string given to '-t'.
+fn parse_types(arg: Option<&str>, stderr: &mut Stderr) -> Vec<Type> {
+    arg.and_then(Type::parse).fail("invalid type string.", stderr)
+}
+
+fn main() {
+    let stdout = io::stdout();
+    let mut stdout = BufWriter::new(stdout.lock());
+    let mut stderr = io::stderr();
+
+    let mut options = Options::default();
+    let mut types = Vec::new();
+    let mut skip = 0;
+    let mut len = u64::MAX;
+    let mut paths = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(a) = args.next() {
+        match a.as_ref() {
+            "-h" | "--help" => {
+                stdout.writeln(HELP).try(&mut stderr);
+                return;
+            },
+            "-A" | "--address-radix" => {
+                let r = args.next();
+                options.address = parse_radix(r.as_ref().map(|r| r.as_ref()), &mut stderr);
+            },
+            "-j" | "--skip-bytes" => {
+                let j = args.next().fail("missing byte count.", &mut stderr);
+                skip = parse_bytes(&j, &mut stderr);
+            },
+            "-N" | "--read-bytes" => {
+                let n = args.next().fail("missing byte count.", &mut stderr);
+                len = parse_bytes(&n, &mut stderr);
+            },
+            "-t" | "--format" => {
+                let t = args.next();
+                types.extend(parse_types(t.as_ref().map(|t| t.as_ref()), &mut stderr));
+            },
+            "-w" | "--width" => {
+                let w = args.next().fail("missing byte count.", &mut stderr);
+                options.cols = parse_bytes(&w, &mut stderr) as usize;
+            },
+            "-b" => types.push(Type::Octal(1)),
+            "-c" => types.push(Type::Char),
+            "-d" => types.push(Type::Unsigned(2)),
+            "-o" => types.push(Type::Octal(2)),
+            "-s" => types.push(Type::Signed(2)),
+            "-x" => types.push(Type::Hex(2)),
+            "-v" | "--output-duplicates" => options.squeeze = false,
+            a if a.starts_with("--address-radix=") => {
+                options.address = parse_radix(Some(&a[16..]), &mut stderr);
+            },
+            a if a.starts_with("-A") => options.address = parse_radix(Some(&a[2..]), &mut stderr),
+            a if a.starts_with("--skip-bytes=") => skip = parse_bytes(&a[13..], &mut stderr),
+            a if a.starts_with("--read-bytes=") => len = parse_bytes(&a[13..], &mut stderr),
+            a if a.starts_with("--format=") => {
+                types.extend(parse_types(Some(&a[9..]), &mut stderr));
+            },
+            a if a.starts_with("-t") => types.extend(parse_types(Some(&a[2..]), &mut stderr)),
+            a if a.starts_with("--width=") => {
+                options.cols = parse_bytes(&a[8..], &mut stderr) as usize;
+            },
+            a if a.starts_with("-j") => skip = parse_bytes(&a[2..], &mut stderr),
+            a if a.starts_with("-N") => len = parse_bytes(&a[2..], &mut stderr),
+            a if a.starts_with("-w") => options.cols = parse_bytes(&a[2..], &mut stderr) as usize,
+            f => paths.push(f.to_owned()),
+        }
+    }
+
+    if !types.is_empty() {
+        options.types = types;
+    }
+    if options.cols == 0 || options.types.iter().any(|t| options.cols % t.size() != 0) {
+        fail("the width must be a positive multiple of the sizes of the types.", &mut stderr);
+    }
+
+    // The files are dumped one after another, as a single input.
+    let mut input: Box<dyn Read> = Box::new(io::empty());
+    let mut skipped = false;
+    if paths.is_empty() {
+        paths.push("-".to_owned());
+    }
+    for (n, path) in paths.iter().enumerate() {
+        let next: Box<dyn Read> = if path == "-" {
+            Box::new(io::stdin())
+        } else {
+            let mut file = fs::File::open(path).try(&mut stderr);
+            if n == 0 && paths.len() == 1 {
+                // A single file is seeked, rather than read, to the start of the dump.
+                let size = file.seek(SeekFrom::End(0)).try(&mut stderr);
+                if skip > size {
+                    fail(SKIP_PAST_END, &mut stderr);
+                }
+                file.seek(SeekFrom::Start(skip)).try(&mut stderr);
+                skipped = true;
+            }
+            Box::new(file)
+        };
+        input = Box::new(input.chain(next));
+    }
+    if !skipped {
+        let n = io::copy(&mut input.by_ref().take(skip), &mut io::sink()).try(&mut stderr);
+        if n < skip {
+            fail(SKIP_PAST_END, &mut stderr);
+        }
+    }
+
+    let mut dumper = Dumper::with_address(stdout, options, skip);
+    io::copy(&mut input.take(len), &mut dumper).try(&mut stderr);
+    dumper.finish().try(&mut stderr);
+}
@@ -2,21 +2,22 @@ extern crate binutils;
 
 use std::env;
 use std::fs;
-use std::io;
+use std::io::{self, BufWriter, Stderr, Write};
 
-use binutils::strings::read;
+use binutils::strings::{read, Encoding, Options, Radix, Unicode};
 use binutils::extra::option::OptionalExt;
 use binutils::extra::io::{WriteExt, fail};
 
-const HELP: &'static [u8] = br#"
+const HELP: &[u8] = br#"
     NAME
         strings - inspect a binary file for strings of printable characters.
     SYNOPSIS
-        strings [-h | --help] [FILE]
+        strings [-h | --help] [-n MIN | --bytes=MIN | -MIN] [-t RADIX | --radix=RADIX | -o]
+                [-e ENCODING | --encoding=ENCODING] [-U MODE | --unicode=MODE] [FILE]
     DESCRIPTION
-        This utility will read the file from the path given in the argument. If no argument is given, 'strings' will read from the standard input. The byte stream is then inspected for contiguous, printable ASCII characters of length 4 or more. These strings of printable characters are written to the standard output. Each contiguous strings are seperated by a newline (0x0A).
+        This utility will read the file from the path given in the argument. If no argument is given, 'strings' will read from the standard input. The byte stream is then inspected for contiguous, printable ASCII characters of length 4 or more (or the length given by '-n'). These strings of printable characters are written to the standard output. Each contiguous strings are seperated by a newline (0x0A).
 
-        This utility is useful for inspecting binary files for human readable information, to determine the contents. Note that all non-ASCII characters are treated as non-printable, due to the numerous false positives otherwise.
+        This utility is useful for inspecting binary files for human readable information, to determine the contents. Note that all non-ASCII characters are treated as non-printable by default, due to the numerous false positives otherwise. The '-U' option enables UTF-8 decoding.
 
         This is a clone of GNU strings, though they differ in a number of ways.
 
@@ -24,6 +25,21 @@ const HELP: &'static [u8] = br#"
         -h
         --help
             Print this manual page.
+        -n MIN
+        --bytes=MIN
+        -MIN
+            Print sequences of printable characters that are at least MIN characters long, instead of 4. A MIN of zero is treated as one.
+        -t RADIX
+        --radix=RADIX
+            Print the offset within the file before each string. RADIX is a single character, 'd' for decimal, 'o' for octal or 'x' for hexadecimal.
+        -o
+            Like '-t o'.
+        -e ENCODING
+        --encoding=ENCODING
+            Search for strings in the given character encoding. 's' is 7-bit ASCII (the default), 'S' is 8-bit bytes, 'b' and 'l' are 16-bit big- and little-endian code units, and 'B' and 'L' are 32-bit big- and little-endian code units. Wide strings may start at any byte offset. Wide characters are written as UTF-8.
+        -U MODE
+        --unicode=MODE
+            Control the treatment of multibyte UTF-8 sequences in the 's' and 'S' encodings. 'default' treats them like any other byte, 'invalid' treats them as non-printable, and 'locale', 'escape' and 'hex' decode them, such that non-control characters count as a single printable character. These are written as UTF-8, as '\uXXXX' escapes or as hexadecimal bytes enclosed in '<>', respectively.
     AUTHOR
         This program was written by Ticki. Bugs should be reported in the Github repository, 'redox-os/binutils'.
     COPYRIGHT
@@ -36,29 +52,125 @@ const HELP: &'static [u8] = br#"
         THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 "#;
 
+/// Parse the minimum string length given to '-n'.
+fn parse_min(arg: Option<&str>, stderr: &mut Stderr) -> usize {
+    match arg.and_then(|n| n.parse().ok()) {
+        Some(n) => n,
+        None => fail("invalid minimum string length.", stderr),
+    }
+}
+
+/// Parse the offset radix given to '-t'.
+fn parse_radix(arg: Option<&str>, stderr: &mut Stderr) -> Radix {
+    match arg {
+        Some("d") => Radix::Decimal,
+        Some("o") => Radix::Octal,
+        Some("x") => Radix::Hexadecimal,
+        _ => fail("invalid radix, expected 'd', 'o' or 'x'.", stderr),
+    }
+}
+
+/// Parse the character encoding given to '-e'.
+fn parse_encoding(arg: Option<&str>, stderr: &mut Stderr) -> Encoding {
+    match arg {
+        Some("s") => Encoding::Ascii,
+        Some("S") => Encoding::EightBit,
+        Some("b") => Encoding::Utf16Be,
+        Some("l") => Encoding::Utf16Le,
+        Some("B") => Encoding::Ucs4Be,
+        Some("L") => Encoding::Ucs4Le,
+        _ => fail("invalid encoding, expected one of 's', 'S', 'b', 'l', 'B' or 'L'.", stderr),
+    }
+}
+
+/// Parse the UTF-8 treatment given to '-U'.
+fn parse_unicode(arg: Option<&str>, stderr: &mut Stderr) -> Unicode {
+    match arg {
+        Some("d") | Some("default") => Unicode::Default,
+        Some("l") | Some("locale") => Unicode::Locale,
+        Some("e") | Some("escape") => Unicode::Escape,
+        Some("i") | Some("invalid") => Unicode::Invalid,
+        Some("x") | Some("hex") => Unicode::Hex,
+        _ => fail("invalid unicode mode, expected 'default', 'locale', 'escape', 'invalid' or 'hex'.",
+                  stderr),
+    }
+}
+
 fn main() {
     let stdout = io::stdout();
-    let mut stdout = stdout.lock();
+    let mut stdout = BufWriter::new(stdout.lock());
     let mut stderr = io::stderr();
-    let mut args = env::args();
 
-    if args.len() > 2 {
-        fail("too many arguments.", &mut stderr);
-    }
+    let mut options = Options::default();
+    let mut path = None;
 
-    match args.nth(1) {
-        None => {
-            let stdin = io::stdin();
-            read(stdin.lock(), stdout, stderr);
-        }
-        Some(a) => match a.as_ref() {
+    let mut args = env::args().skip(1);
+    while let Some(a) = args.next() {
+        match a.as_ref() {
             "-h" | "--help" => {
                 stdout.writeln(HELP).try(&mut stderr);
+                return;
+            },
+            "-n" | "--bytes" => {
+                let n = args.next();
+                options.min = parse_min(n.as_ref().map(|n| n.as_ref()), &mut stderr);
+            },
+            "-t" | "--radix" => {
+                let r = args.next();
+                options.radix = Some(parse_radix(r.as_ref().map(|r| r.as_ref()), &mut stderr));
+            },
+            "-e" | "--encoding" => {
+                let e = args.next();
+                options.encoding = parse_encoding(e.as_ref().map(|e| e.as_ref()), &mut stderr);
+            },
+            "-U" | "--unicode" => {
+                let u = args.next();
+                options.unicode = parse_unicode(u.as_ref().map(|u| u.as_ref()), &mut stderr);
+            },
+            "-o" => options.radix = Some(Radix::Octal),
+            a if a.starts_with("--radix=") => {
+                options.radix = Some(parse_radix(Some(&a[8..]), &mut stderr));
+            },
+            a if a.starts_with("-t") => {
+                options.radix = Some(parse_radix(Some(&a[2..]), &mut stderr));
+            },
+            a if a.starts_with("--encoding=") => {
+                options.encoding = parse_encoding(Some(&a[11..]), &mut stderr);
+            },
+            a if a.starts_with("-e") => {
+                options.encoding = parse_encoding(Some(&a[2..]), &mut stderr);
+            },
+            a if a.starts_with("--unicode=") => {
+                options.unicode = parse_unicode(Some(&a[10..]), &mut stderr);
+            },
+            a if a.starts_with("-U") => {
+                options.unicode = parse_unicode(Some(&a[2..]), &mut stderr);
+            },
+            a if a.starts_with("--bytes=") => options.min = parse_min(Some(&a[8..]), &mut stderr),
+            a if a.starts_with("-n") => options.min = parse_min(Some(&a[2..]), &mut stderr),
+            // GNU strings also accepts the old '-MIN' form.
+            a if a.len() > 1 && a.starts_with('-') && a[1..].bytes().all(|b| b.is_ascii_digit()) => {
+                options.min = parse_min(Some(&a[1..]), &mut stderr);
             },
             f => {
-                let file = fs::File::open(f).try(&mut stderr);
-                read(file, stdout, stderr);
-            }
-        },
+                if path.is_some() {
+                    fail("too many arguments.", &mut stderr);
+                }
+                path = Some(f.to_owned());
+            },
+        }
     }
+
+    match path {
+        None => {
+            let stdin = io::stdin();
+            read(stdin.lock(), &mut stdout, options).try(&mut stderr);
+        }
+        Some(f) => {
+            let file = fs::File::open(f).try(&mut stderr);
+            read(file, &mut stdout, options).try(&mut stderr);
+        }
+    }
+
+    stdout.flush().try(&mut stderr);
 }
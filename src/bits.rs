@@ -1,22 +1,664 @@
-use std::ops::Range;
+use std::io::{self, Read, Write};
+use std::ops::{Range, RangeInclusive};
+
+use error::{Error, Result};
+
+/// The numbering of the bits of an integer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BitOrder {
+    /// Bit 0 is the most significant bit, as in the diagrams of most hardware manuals.
+    Msb0,
+    /// Bit 0 is the least significant bit, such that bit `n` has the value `1 << n`.
+    Lsb0,
+}
+
+/// A range of bit indices, which can slice an integer.
+pub trait BitRange {
+    /// The first bit index and the index following the last one, or `None` if the end can not be
+    /// represented.
+    fn bounds(&self) -> Option<(u32, u32)>;
+}
+
+impl BitRange for Range<u32> {
+    fn bounds(&self) -> Option<(u32, u32)> {
+        Some((self.start, self.end))
+    }
+}
+
+impl BitRange for RangeInclusive<u32> {
+    fn bounds(&self) -> Option<(u32, u32)> {
+        self.end().checked_add(1).map(|end| (*self.start(), end))
+    }
+}
 
 /// A trait for slicing integer's bits
 pub trait BitSlice : Sized {
-    /// Slice an integer's bits by a range
-    fn bit_slice(self, range: Range<Self>) -> Self;
+    /// The number of bits of the integer.
+    const BITS: u32;
+
+    /// Slice an integer's bits by a range, with the bits numbered in the given order
+    ///
+    /// The bits are returned in their order of significance, shifted down to the least
+    /// significant bits. An empty range gives zero, whereas `None` is returned if the range is
+    /// reversed or goes beyond the bits of the integer.
+    fn bit_slice<R: BitRange>(self, range: R, order: BitOrder) -> Option<Self>;
+}
+
+macro_rules! impl_bit_slice {
+    ($($ty:ty),*) => {$(
+        impl BitSlice for $ty {
+            const BITS: u32 = 8 * ::std::mem::size_of::<$ty>() as u32;
+
+            fn bit_slice<R: BitRange>(self, range: R, order: BitOrder) -> Option<Self> {
+                let (start, end) = range.bounds()?;
+                if start > end || end > Self::BITS {
+                    return None;
+                }
+                if start == end {
+                    // Shifting by the full width would overflow.
+                    return Some(0);
+                }
+
+                let shift = match order {
+                    BitOrder::Msb0 => Self::BITS - end,
+                    BitOrder::Lsb0 => start,
+                };
+                let mask = <$ty>::MAX >> (Self::BITS - (end - start));
+                Some(self >> shift & mask)
+            }
+        }
+    )*};
+}
+
+impl_bit_slice!(u8, u16, u32, u64, u128, usize);
+
+/// Encode a slice of bytes as binary digits
+///
+/// Every byte is written as eight digits to `dst`, the most significant bit first. `dst` must be
+/// at least eight times as long as `src`.
+///
+/// # Panics
+///
+/// Panics if `dst` is too short.
+pub fn encode_binary(src: &[u8], dst: &mut [u8]) {
+    assert!(dst.len() >= src.len() * 8, "the binary output buffer is too short");
+
+    for (&b, digits) in src.iter().zip(dst.chunks_mut(8)) {
+        for (i, d) in digits.iter_mut().enumerate() {
+            // The range is always within the byte.
+            *d = b'0' + b.bit_slice(i as u32..i as u32 + 1, BitOrder::Msb0).unwrap();
+        }
+    }
+}
+
+/// Decode a slice of binary digits to bytes
+///
+/// Every eight digits in `src`, the most significant bit first, are written as a byte to `dst`,
+/// which must be at least an eighth as long as `src`. The offset of the first invalid digit is
+/// reported in the error, in which case the bytes preceding it have been written to `dst`.
+///
+/// # Panics
+///
+/// Panics if the length of `src` is not a multiple of eight, or if `dst` is too short.
+pub fn decode_binary(src: &[u8], dst: &mut [u8]) -> Result<()> {
+    assert!(src.len().is_multiple_of(8), "the binary digits do not form whole bytes");
+    assert!(dst.len() >= src.len() / 8, "the byte output buffer is too short");
+
+    for (n, digits) in src.chunks(8).enumerate() {
+        let mut byte = 0;
+        for (i, &d) in digits.iter().enumerate() {
+            match d {
+                b'0' | b'1' => byte = byte << 1 | (d - b'0'),
+                _ => {
+                    return Err(Error::InvalidBinaryDigit {
+                        offset: (n * 8 + i) as u64,
+                        byte: d,
+                    });
+                },
+            }
+        }
+        dst[n] = byte;
+    }
+
+    Ok(())
+}
+
+/// The largest number of bits read or written at once.
+const MAX_BITS: u32 = 64;
+
+/// The error of a bit count beyond `MAX_BITS`.
+fn too_many_bits() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "at most 64 bits can be read or written at once")
+}
+
+/// The mask of the `n` least significant bits.
+#[inline]
+fn mask(n: u32) -> u128 {
+    (1 << n) - 1
+}
+
+/// A reader of values of any number of bits from a byte stream.
+///
+/// In `Msb0` order, the bits of every byte are read from the most significant one, and the first
+/// bit read is the most significant bit of a value, as in most media formats. In `Lsb0` order,
+/// the bits are read from the least significant one, and the first bit read is the least
+/// significant bit of a value, as in DEFLATE.
+///
+/// The inner reader is read a byte at a time, so it should be buffered.
+pub struct BitReader<R> {
+    inner: R,
+    order: BitOrder,
+    /// The bits read from the inner reader, but not yet consumed.
+    buf: u128,
+    /// The number of bits in `buf`.
+    len: u32,
+}
+
+impl<R: Read> BitReader<R> {
+    /// Create a reader of the bits of `inner` in the given order.
+    pub fn new(inner: R, order: BitOrder) -> BitReader<R> {
+        BitReader {
+            inner,
+            order,
+            buf: 0,
+            len: 0,
+        }
+    }
+
+    /// Get a reference to the inner reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Unwrap the inner reader. The bits of a partially read byte are lost.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Read bytes from the inner reader, until at least `n` bits are buffered.
+    fn fill(&mut self, n: u32) -> io::Result<()> {
+        while self.len < n {
+            let mut byte = [0];
+            match self.inner.read(&mut byte) {
+                Ok(0) => {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                                              "the bit stream ended"));
+                },
+                Ok(_) => {
+                    self.buf = match self.order {
+                        BitOrder::Msb0 => self.buf << 8 | byte[0] as u128,
+                        BitOrder::Lsb0 => self.buf | (byte[0] as u128) << self.len,
+                    };
+                    self.len += 8;
+                },
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read the next `n` bits as a value, without consuming them.
+    ///
+    /// At most 64 bits can be peeked. If the stream ends before, an error of the kind
+    /// `UnexpectedEof` is returned, and the bits are left to be read.
+    pub fn peek_bits(&mut self, n: u32) -> io::Result<u64> {
+        if n > MAX_BITS {
+            return Err(too_many_bits());
+        }
+        self.fill(n)?;
+
+        Ok(match self.order {
+            BitOrder::Msb0 => self.buf >> (self.len - n) & mask(n),
+            BitOrder::Lsb0 => self.buf & mask(n),
+        } as u64)
+    }
+
+    /// Skip `n` bits, which must have been peeked.
+    fn consume(&mut self, n: u32) {
+        self.len -= n;
+        match self.order {
+            BitOrder::Msb0 => self.buf &= mask(self.len),
+            BitOrder::Lsb0 => self.buf >>= n,
+        }
+    }
+
+    /// Read the next `n` bits as a value.
+    ///
+    /// At most 64 bits can be read at once. If the stream ends before, an error of the kind
+    /// `UnexpectedEof` is returned, and the bits are left to be read.
+    pub fn read_bits(&mut self, n: u32) -> io::Result<u64> {
+        let value = self.peek_bits(n)?;
+        self.consume(n);
+
+        Ok(value)
+    }
+
+    /// Read a single bit.
+    pub fn read_bit(&mut self) -> io::Result<bool> {
+        self.read_bits(1).map(|b| b == 1)
+    }
+
+    /// Is the next bit the first bit of a byte?
+    pub fn is_aligned(&self) -> bool {
+        self.len.is_multiple_of(8)
+    }
+
+    /// Skip the rest of the current byte, such that the next bit is the first bit of a byte.
+    pub fn align(&mut self) {
+        let n = self.len % 8;
+        self.consume(n);
+    }
+
+    /// Read the prefix of an exponential-Golomb code, returning the value plus one.
+    fn read_golomb(&mut self) -> io::Result<u128> {
+        let mut zeros = 0;
+        while !self.read_bit()? {
+            zeros += 1;
+            if zeros > MAX_BITS {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                          "exponential-Golomb code out of range"));
+            }
+        }
+
+        Ok(1 << zeros | self.read_bits(zeros)? as u128)
+    }
+
+    /// Read an unsigned exponential-Golomb code of order zero, as used by H.264.
+    ///
+    /// A value of `n` is coded as `m` zero bits followed by the `m + 1` bits of `n + 1`. In
+    /// `Lsb0` order, the bits following the leading one bit are read as a single value, the least
+    /// significant bit first. A code of a value beyond `u64` gives an error of the kind
+    /// `InvalidData`.
+    pub fn read_exp_golomb(&mut self) -> io::Result<u64> {
+        let value = self.read_golomb()? - 1;
+        if value > u64::MAX as u128 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                      "exponential-Golomb code out of range"));
+        }
+
+        Ok(value as u64)
+    }
+
+    /// Read a signed exponential-Golomb code of order zero.
+    ///
+    /// The positive values are coded as the odd unsigned values, and the others as the even
+    /// ones: 0, 1, -1, 2, -2 and so on.
+    pub fn read_signed_exp_golomb(&mut self) -> io::Result<i64> {
+        let code = (self.read_golomb()? - 1) as i128;
+        let value = if code % 2 == 1 {
+            (code + 1) / 2
+        } else {
+            -code / 2
+        };
+        if value > i64::MAX as i128 || value < i64::MIN as i128 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                      "exponential-Golomb code out of range"));
+        }
+
+        Ok(value as i64)
+    }
+
+    /// Read a variable-length integer in the LEB128 format.
+    ///
+    /// Every group of 8 bits holds 7 bits of the value, the least significant first, and has
+    /// its high bit set if another group follows. The groups need not be aligned to bytes. A
+    /// value beyond `u64` gives an error of the kind `InvalidData`.
+    pub fn read_varint(&mut self) -> io::Result<u64> {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let group = self.read_bits(8)?;
+            // Only the least significant bit of the tenth group fits, and no group follows it.
+            if shift == 63 && group > 1 {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "varint out of range"));
+            }
+
+            value |= (group & 0x7F) << shift;
+            shift += 7;
+            if group & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+    }
 }
 
-impl BitSlice for u8 {
-    fn bit_slice(self, range: Range<Self>) -> Self {
-        self << range.start >> range.start >> (8 - range.end)
+/// A writer of values of any number of bits to a byte stream.
+///
+/// The bits are ordered like those of `BitReader`. Whole bytes are written to the inner writer
+/// as they are completed, and `finish` must be called to write the last, partial byte.
+pub struct BitWriter<W> {
+    inner: W,
+    order: BitOrder,
+    /// The bits written, which do not yet form a byte.
+    buf: u128,
+    /// The number of bits in `buf`.
+    len: u32,
+}
+
+impl<W: Write> BitWriter<W> {
+    /// Create a writer of bits to `inner` in the given order.
+    pub fn new(inner: W, order: BitOrder) -> BitWriter<W> {
+        BitWriter {
+            inner,
+            order,
+            buf: 0,
+            len: 0,
+        }
+    }
+
+    /// Get a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Write the `n` least significant bits of the value. The other bits are ignored.
+    ///
+    /// At most 64 bits can be written at once.
+    pub fn write_bits(&mut self, value: u64, n: u32) -> io::Result<()> {
+        if n > MAX_BITS {
+            return Err(too_many_bits());
+        }
+
+        let value = value as u128 & mask(n);
+        self.buf = match self.order {
+            BitOrder::Msb0 => self.buf << n | value,
+            BitOrder::Lsb0 => self.buf | value << self.len,
+        };
+        self.len += n;
+
+        // Write the completed bytes at once.
+        let mut bytes = [0; 9];
+        let mut count = 0;
+        while self.len >= 8 {
+            self.len -= 8;
+            bytes[count] = match self.order {
+                BitOrder::Msb0 => (self.buf >> self.len) as u8,
+                BitOrder::Lsb0 => {
+                    let byte = self.buf as u8;
+                    self.buf >>= 8;
+                    byte
+                },
+            };
+            count += 1;
+        }
+        if let BitOrder::Msb0 = self.order {
+            self.buf &= mask(self.len);
+        }
+
+        self.inner.write_all(&bytes[..count])
+    }
+
+    /// Write a single bit.
+    pub fn write_bit(&mut self, bit: bool) -> io::Result<()> {
+        self.write_bits(bit as u64, 1)
+    }
+
+    /// Is the next bit the first bit of a byte?
+    pub fn is_aligned(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Pad the current byte with zero bits, such that the next bit is the first bit of a byte.
+    pub fn align(&mut self) -> io::Result<()> {
+        let n = (8 - self.len % 8) % 8;
+        self.write_bits(0, n)
+    }
+
+    /// Write an exponential-Golomb code of the value plus one.
+    ///
+    /// The leading one bit is written on its own, and the bits following it as a single value,
+    /// such that the code is the same in both orders.
+    fn write_golomb(&mut self, value: u128) -> io::Result<()> {
+        let zeros = 127 - value.leading_zeros();
+        self.write_bits(0, zeros)?;
+        self.write_bit(true)?;
+        self.write_bits((value & mask(zeros)) as u64, zeros)
+    }
+
+    /// Write an unsigned exponential-Golomb code of order zero, as read by
+    /// `BitReader::read_exp_golomb`.
+    pub fn write_exp_golomb(&mut self, value: u64) -> io::Result<()> {
+        self.write_golomb(value as u128 + 1)
+    }
+
+    /// Write a signed exponential-Golomb code of order zero, as read by
+    /// `BitReader::read_signed_exp_golomb`.
+    pub fn write_signed_exp_golomb(&mut self, value: i64) -> io::Result<()> {
+        let value = value as i128;
+        let code = if value > 0 {
+            value * 2 - 1
+        } else {
+            -value * 2
+        };
+        self.write_golomb(code as u128 + 1)
+    }
+
+    /// Write a variable-length integer in the LEB128 format, as read by `BitReader::read_varint`.
+    pub fn write_varint(&mut self, mut value: u64) -> io::Result<()> {
+        loop {
+            let group = value & 0x7F;
+            value >>= 7;
+            if value == 0 {
+                return self.write_bits(group, 8);
+            }
+            self.write_bits(group | 0x80, 8)?;
+        }
+    }
+
+    /// Pad the last byte with zero bits and write it, flush the inner writer and return it.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.align()?;
+        self.inner.flush()?;
+
+        Ok(self.inner)
     }
 }
 
 #[test]
 fn bit_slice_test() {
-    assert_eq!(0b10000100.bit_slice(1..2), 0);
-    assert_eq!(0b10000100.bit_slice(0..2), 0b10);
-    assert_eq!(0b10001110.bit_slice(4..8), 0b1110);
-    assert_eq!(0b00001110.bit_slice(0..4), 0);
-    assert_eq!(0b00001110.bit_slice(3..8), 0b1110);
+    assert_eq!(0b10000100u8.bit_slice(1..2, BitOrder::Msb0), Some(0));
+    assert_eq!(0b10000100u8.bit_slice(0..2, BitOrder::Msb0), Some(0b10));
+    assert_eq!(0b10001110u8.bit_slice(4..8, BitOrder::Msb0), Some(0b1110));
+    assert_eq!(0b00001110u8.bit_slice(0..4, BitOrder::Msb0), Some(0));
+    assert_eq!(0b00001110u8.bit_slice(3..8, BitOrder::Msb0), Some(0b1110));
+
+    assert_eq!(0b10000100u8.bit_slice(2..3, BitOrder::Lsb0), Some(1));
+    assert_eq!(0b10001110u8.bit_slice(1..=3, BitOrder::Lsb0), Some(0b111));
+    assert_eq!(0b10001110u8.bit_slice(4..=7, BitOrder::Msb0), Some(0b1110));
+}
+
+#[test]
+fn bit_slice_edges_test() {
+    // Full and empty ranges used to overflow the shifts.
+    assert_eq!(0xA5u8.bit_slice(0..8, BitOrder::Msb0), Some(0xA5));
+    assert_eq!(0xA5u8.bit_slice(0..8, BitOrder::Lsb0), Some(0xA5));
+    assert_eq!(0xA5u8.bit_slice(0..0, BitOrder::Msb0), Some(0));
+    assert_eq!(0xA5u8.bit_slice(8..8, BitOrder::Lsb0), Some(0));
+    assert_eq!(0xA5u8.bit_slice(0..=7, BitOrder::Lsb0), Some(0xA5));
+
+    // Out of range and reversed ranges.
+    assert_eq!(0xA5u8.bit_slice(0..9, BitOrder::Msb0), None);
+    assert_eq!(0xA5u8.bit_slice(8..=8, BitOrder::Lsb0), None);
+    let (start, end) = (5, 3);
+    assert_eq!(0xA5u8.bit_slice(start..end, BitOrder::Lsb0), None);
+    assert_eq!(0xA5u8.bit_slice(0..=u32::MAX, BitOrder::Lsb0), None);
+}
+
+#[test]
+fn bit_slice_widths_test() {
+    assert_eq!(0xBEEFu16.bit_slice(4..12, BitOrder::Lsb0), Some(0xEE));
+    assert_eq!(0xBEEFu16.bit_slice(0..4, BitOrder::Msb0), Some(0xB));
+    assert_eq!(0xDEAD_BEEFu32.bit_slice(16..32, BitOrder::Lsb0), Some(0xDEAD));
+    assert_eq!(0xDEAD_BEEFu32.bit_slice(16..=31, BitOrder::Msb0), Some(0xBEEF));
+    assert_eq!(0x8000_0000_0000_0001u64.bit_slice(63..64, BitOrder::Lsb0), Some(1));
+    assert_eq!(0x8000_0000_0000_0001u64.bit_slice(0..64, BitOrder::Msb0),
+               Some(0x8000_0000_0000_0001));
+    assert_eq!((1u128 << 100).bit_slice(100..=100, BitOrder::Lsb0), Some(1));
+    assert_eq!((1u128 << 100).bit_slice(27..28, BitOrder::Msb0), Some(1));
+    assert_eq!(u128::MAX.bit_slice(0..129, BitOrder::Msb0), None);
+    assert_eq!(usize::MAX.bit_slice(0..usize::BITS, BitOrder::Lsb0),
+               Some(usize::MAX));
+    assert_eq!(<usize as BitSlice>::BITS, 8 * ::std::mem::size_of::<usize>() as u32);
+}
+
+#[test]
+fn binary_test() {
+    let mut digits = [0; 24];
+    encode_binary(&[0b10000100, 0, 0xFF], &mut digits);
+    assert_eq!(&digits, b"100001000000000011111111");
+
+    let mut bytes = [0; 3];
+    decode_binary(&digits, &mut bytes).unwrap();
+    assert_eq!(bytes, [0b10000100, 0, 0xFF]);
+
+    for b in 0..=255 {
+        encode_binary(&[b], &mut digits);
+        decode_binary(&digits[..8], &mut bytes).unwrap();
+        assert_eq!(bytes[0], b);
+    }
+
+    match decode_binary(b"0000000100000201", &mut bytes) {
+        Err(Error::InvalidBinaryDigit { offset: 13, byte: b'2' }) => assert_eq!(bytes[0], 1),
+        res => panic!("unexpected result {:?}", res),
+    }
+}
+
+#[test]
+fn bit_stream_vectors_test() {
+    let mut writer = BitWriter::new(Vec::new(), BitOrder::Msb0);
+    writer.write_bits(0b101, 3).unwrap();
+    writer.write_bits(0b11111, 5).unwrap();
+    writer.write_bits(0xABC, 12).unwrap();
+    assert!(!writer.is_aligned());
+    assert_eq!(writer.finish().unwrap(), [0xBF, 0xAB, 0xC0]);
+
+    let mut writer = BitWriter::new(Vec::new(), BitOrder::Lsb0);
+    writer.write_bits(0b101, 3).unwrap();
+    writer.write_bits(0b11111, 5).unwrap();
+    writer.write_bits(0xABC, 12).unwrap();
+    assert_eq!(writer.finish().unwrap(), [0xFD, 0xBC, 0x0A]);
+
+    // The exponential-Golomb codes 1, 010, 011, 00100 and 00101.
+    let mut writer = BitWriter::new(Vec::new(), BitOrder::Msb0);
+    for n in 0..4 {
+        writer.write_exp_golomb(n).unwrap();
+    }
+    writer.write_signed_exp_golomb(-2).unwrap();
+    assert_eq!(writer.finish().unwrap(), [0b10100110, 0b01000010, 0b10000000]);
+
+    let mut writer = BitWriter::new(Vec::new(), BitOrder::Lsb0);
+    writer.write_varint(300).unwrap();
+    writer.write_varint(0).unwrap();
+    assert_eq!(writer.finish().unwrap(), [0xAC, 0x02, 0x00]);
+
+    let mut reader = BitReader::new(&[0xBF, 0xAB, 0xC0][..], BitOrder::Msb0);
+    assert_eq!(reader.peek_bits(3).unwrap(), 0b101);
+    assert_eq!(reader.read_bits(3).unwrap(), 0b101);
+    assert!(reader.read_bit().unwrap());
+    reader.align();
+    assert!(reader.is_aligned());
+    assert_eq!(reader.read_bits(16).unwrap(), 0xABC0);
+}
+
+#[test]
+fn bit_reader_errors_test() {
+    let mut reader = BitReader::new(&[0xFF][..], BitOrder::Lsb0);
+    assert_eq!(reader.peek_bits(9).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    // The bits are still there after a failed read.
+    assert_eq!(reader.read_bits(8).unwrap(), 0xFF);
+    assert_eq!(reader.read_bit().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    assert_eq!(reader.read_bits(65).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+    let zeros = [0; 10];
+    let mut reader = BitReader::new(&zeros[..], BitOrder::Msb0);
+    assert_eq!(reader.read_exp_golomb().unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+    let overlong = [0xFF; 11];
+    let mut reader = BitReader::new(&overlong[..], BitOrder::Lsb0);
+    assert_eq!(reader.read_varint().unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+    let mut writer = BitWriter::new(Vec::new(), BitOrder::Msb0);
+    assert_eq!(writer.write_bits(0, 65).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn bit_stream_roundtrip_test() {
+    /// An operation on a bit stream.
+    #[derive(Debug)]
+    enum Op {
+        Bits(u64, u32),
+        Peek(u64, u32),
+        Align,
+        Golomb(u64),
+        SignedGolomb(i64),
+        Varint(u64),
+    }
+
+    // A xorshift generator, such that the test is deterministic.
+    let mut state = 0x2545_F491_4F6C_DD1Du64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    for round in 0..200 {
+        let order = if round % 2 == 0 { BitOrder::Msb0 } else { BitOrder::Lsb0 };
+        let ops: Vec<Op> = (0..next() % 100).map(|_| {
+            // Favour small values, which exercise the short codes.
+            let value = next() >> (next() % 64);
+            match next() % 6 {
+                0 => {
+                    let n = (next() % 65) as u32;
+                    Op::Bits(value & mask(n) as u64, n)
+                },
+                1 => {
+                    let n = (next() % 65) as u32;
+                    Op::Peek(value & mask(n) as u64, n)
+                },
+                2 => Op::Align,
+                3 => Op::Golomb(value),
+                4 => Op::SignedGolomb(value as i64),
+                _ => Op::Varint(value),
+            }
+        }).chain(vec![Op::Golomb(u64::MAX), Op::SignedGolomb(i64::MIN)]).collect();
+
+        let mut writer = BitWriter::new(Vec::new(), order);
+        for op in &ops {
+            match *op {
+                Op::Bits(value, n) | Op::Peek(value, n) => writer.write_bits(value, n).unwrap(),
+                Op::Align => writer.align().unwrap(),
+                Op::Golomb(value) => writer.write_exp_golomb(value).unwrap(),
+                Op::SignedGolomb(value) => writer.write_signed_exp_golomb(value).unwrap(),
+                Op::Varint(value) => writer.write_varint(value).unwrap(),
+            }
+        }
+        let bytes = writer.finish().unwrap();
+
+        let mut reader = BitReader::new(&bytes[..], order);
+        for op in &ops {
+            match *op {
+                Op::Bits(value, n) => assert_eq!(reader.read_bits(n).unwrap(), value, "{:?}", op),
+                Op::Peek(value, n) => {
+                    assert_eq!(reader.peek_bits(n).unwrap(), value, "{:?}", op);
+                    assert_eq!(reader.read_bits(n).unwrap(), value, "{:?}", op);
+                },
+                Op::Align => {
+                    reader.align();
+                    assert!(reader.is_aligned());
+                },
+                Op::Golomb(value) => assert_eq!(reader.read_exp_golomb().unwrap(), value),
+                Op::SignedGolomb(value) => {
+                    assert_eq!(reader.read_signed_exp_golomb().unwrap(), value)
+                },
+                Op::Varint(value) => assert_eq!(reader.read_varint().unwrap(), value),
+            }
+        }
+        // Only the padding of the last byte is left.
+        reader.align();
+        assert_eq!(reader.read_bit().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
 }
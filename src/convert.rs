@@ -1,4 +1,4 @@
-use std::mem;
+use error::{Error, Result};
 
 /// Convert a base 256 (byte) to a hexadecimal representation
 #[inline]
@@ -15,38 +15,584 @@ pub fn hex_to_u8((a, b): (u8, u8)) -> u8 {
 /// Convert an u32 to an byte array. This operation is safe. It is an noop on big-endianness
 #[inline]
 pub fn u32_byte_array(int: u32) -> [u8; 4] {
-    // Safe
-    // ((int >> (32 - 8 - n * 8)) & 255) as u8
-    // or unsafe:
-    unsafe {
-        mem::transmute::<_, [u8; 4]>(int.to_be())
-    }
+    int.to_be_bytes()
+}
+
+/// Convert an u64 to a big-endian byte array
+#[inline]
+pub fn u64_byte_array(int: u64) -> [u8; 8] {
+    int.to_be_bytes()
 }
 
 /// Convert hex to ascii
 #[inline]
 pub fn hex_to_ascii(b: u8) -> u8 {
     match b {
-        0...9 => b'0' + b,
+        0..=9 => b'0' + b,
         _ => b'a' - 10 + b,
     }
 }
 
+/// Convert hex to uppercase ascii
+#[inline]
+pub fn hex_to_ascii_upper(b: u8) -> u8 {
+    match b {
+        0..=9 => b'0' + b,
+        _ => b'A' - 10 + b,
+    }
+}
+
 /// Convert ascii to hex
+///
+/// The input is assumed to be a valid, lowercase hexadecimal digit. Use `try_ascii_to_hex` for
+/// untrusted input.
 #[inline]
 pub fn ascii_to_hex(b: u8) -> u8 {
     match b {
-        b'0'...b'9' => b - b'0',
+        b'0'..=b'9' => b - b'0',
         _ => b - b'a' + 10,
     }
 }
 
+/// Convert ascii to hex, returning `None` if the byte is not a hexadecimal digit
+///
+/// Both lowercase and uppercase digits are accepted.
+#[inline]
+pub fn try_ascii_to_hex(b: u8) -> Option<u8> {
+    match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    }
+}
+
 /// Convert a base 256 (byte) to an octal representation
 #[inline]
 pub fn u8_to_oct(from: u8) -> (u8, u8, u8) {
     (from >> 6, (from >> 3) & 0b111, from & 0b111)
 }
 
+/// The lowercase hexadecimal digits
+const DIGITS_LOWER: &[u8; 16] = b"0123456789abcdef";
+
+/// The uppercase hexadecimal digits
+const DIGITS_UPPER: &[u8; 16] = b"0123456789ABCDEF";
+
+/// The marker for bytes, which are not hexadecimal digits, in `DECODE`
+const INVALID: u8 = 0xFF;
+
+/// The value of every byte as a hexadecimal digit, or `INVALID`
+const DECODE: [u8; 256] = decode_table();
+
+/// Build the `DECODE` table.
+const fn decode_table() -> [u8; 256] {
+    let mut table = [INVALID; 256];
+    let mut n = 0;
+    while n < 16 {
+        table[DIGITS_LOWER[n] as usize] = n as u8;
+        table[DIGITS_UPPER[n] as usize] = n as u8;
+        n += 1;
+    }
+    table
+}
+
+/// Encode a slice of bytes as lowercase hexadecimal
+///
+/// Every byte is written as two digits to `dst`, which must be at least twice as long as `src`.
+/// SIMD instructions are used when the CPU supports them.
+///
+/// # Panics
+///
+/// Panics if `dst` is too short.
+pub fn encode_hex(src: &[u8], dst: &mut [u8]) {
+    encode_with(src, dst, DIGITS_LOWER);
+}
+
+/// Encode a slice of bytes as uppercase hexadecimal
+///
+/// See `encode_hex`.
+pub fn encode_hex_upper(src: &[u8], dst: &mut [u8]) {
+    encode_with(src, dst, DIGITS_UPPER);
+}
+
+/// Encode a slice of bytes with the given digits.
+fn encode_with(src: &[u8], dst: &mut [u8], digits: &[u8; 16]) {
+    assert!(dst.len() >= src.len() * 2, "the hexadecimal output buffer is too short");
+
+    let n = simd::encode(src, dst, digits[10]);
+    for (i, &b) in src[n..].iter().enumerate() {
+        dst[(n + i) * 2] = digits[(b >> 4) as usize];
+        dst[(n + i) * 2 + 1] = digits[(b & 0b1111) as usize];
+    }
+}
+
+/// Decode a slice of hexadecimal digits to bytes
+///
+/// Every pair of digits in `src` is written as a byte to `dst`, which must be at least half as
+/// long as `src`. Both lowercase and uppercase digits are accepted, but nothing else is; the
+/// offset of the first invalid digit is reported in the error, in which case the bytes preceding
+/// the pair containing it have been written to `dst`. SIMD instructions are used when the CPU
+/// supports them.
+///
+/// # Panics
+///
+/// Panics if `dst` is too short.
+pub fn decode_hex(src: &[u8], dst: &mut [u8]) -> Result<()> {
+    assert!(dst.len() >= src.len() / 2, "the byte output buffer is too short");
+
+    let len = src.len() - src.len() % 2;
+    let mut n = simd::decode(&src[..len], dst);
+    while n < len {
+        let h1 = DECODE[src[n] as usize];
+        let h2 = DECODE[src[n + 1] as usize];
+        if h1 == INVALID || h2 == INVALID {
+            let offset = if h1 == INVALID { n } else { n + 1 };
+            return Err(Error::InvalidHexDigit {
+                offset: offset as u64,
+                byte: src[offset],
+            });
+        }
+
+        dst[n / 2] = hex_to_u8((h1, h2));
+        n += 2;
+    }
+
+    if len != src.len() {
+        // The last digit has no pair.
+        let offset = len;
+        return match DECODE[src[offset] as usize] {
+            INVALID => Err(Error::InvalidHexDigit {
+                offset: offset as u64,
+                byte: src[offset],
+            }),
+            _ => Err(Error::UnpairedHexDigit {
+                offset: offset as u64,
+            }),
+        };
+    }
+
+    Ok(())
+}
+
+/// The layout of the hexadecimal written by a `HexEncoder`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HexLayout {
+    /// The maximal line width, if the output is wrapped.
+    pub width: Option<usize>,
+    /// The number of bytes between separators, if the output is grouped.
+    pub group: Option<usize>,
+    /// The separator written between groups.
+    pub separator: String,
+    /// Use uppercase digits.
+    pub upper: bool,
+}
+
+impl Default for HexLayout {
+    /// Continuous lowercase digits, with a single space as the separator.
+    fn default() -> HexLayout {
+        HexLayout {
+            width: None,
+            group: None,
+            separator: " ".to_owned(),
+            upper: false,
+        }
+    }
+}
+
+/// An incremental hexadecimal encoder, which wraps and groups its output
+///
+/// The input may be split into blocks anywhere; the position on the current line is kept between
+/// blocks, so the output is the same as if the input was encoded at once. Lines are only broken
+/// between bytes, and the separator is dropped at a line break.
+#[derive(Debug)]
+pub struct HexEncoder {
+    layout: HexLayout,
+    /// The number of bytes written on the current line.
+    bytes: usize,
+    /// The number of characters written on the current line.
+    col: usize,
+}
+
+impl HexEncoder {
+    /// Create an encoder at the start of a line.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the width or the group size is zero.
+    pub fn new(layout: HexLayout) -> HexEncoder {
+        assert!(layout.width != Some(0) && layout.group != Some(0),
+                "the width and group size must be positive");
+
+        HexEncoder {
+            layout,
+            bytes: 0,
+            col: 0,
+        }
+    }
+
+    /// Encode the next block of the input, appending the hexadecimal to `out`.
+    pub fn encode(&mut self, block: &[u8], out: &mut Vec<u8>) {
+        let layout = &self.layout;
+        let separator = layout.separator.as_bytes();
+
+        // Encode the whole block at once, and lay out the digits afterwards.
+        let start = out.len();
+        out.resize(start + block.len() * 2, 0);
+        if layout.upper {
+            encode_hex_upper(block, &mut out[start..]);
+        } else {
+            encode_hex(block, &mut out[start..]);
+        }
+        if layout.width.is_none() && layout.group.is_none() {
+            // Continuous output is not broken into lines at all, but still ended by `finish`.
+            self.col = self.col.saturating_add(block.len() * 2);
+            return;
+        }
+        let digits = out.split_off(start);
+
+        for pair in digits.chunks(2) {
+            let bytes = self.bytes;
+            let separate = bytes != 0 && layout.group.is_some_and(|n| bytes.is_multiple_of(n));
+            let len = if separate { separator.len() + 2 } else { 2 };
+            if layout.width.is_some_and(|w| self.col != 0 && self.col + len > w) {
+                // Break the line, dropping the separator.
+                out.push(b'\n');
+                self.bytes = 0;
+                self.col = 0;
+            } else if separate {
+                out.extend_from_slice(separator);
+                self.col += separator.len();
+            }
+
+            out.extend_from_slice(pair);
+            self.bytes += 1;
+            self.col += 2;
+        }
+    }
+
+    /// End the last line, unless the input was empty.
+    pub fn finish(&mut self, out: &mut Vec<u8>) {
+        if self.col != 0 {
+            out.push(b'\n');
+        }
+        self.bytes = 0;
+        self.col = 0;
+    }
+}
+
+/// Decode a single hexadecimal digit, found at `offset` in the input.
+fn digit(b: u8, offset: u64) -> Result<u8> {
+    try_ascii_to_hex(b).ok_or(Error::InvalidHexDigit {
+        offset,
+        byte: b,
+    })
+}
+
+/// Is this byte whitespace or a separator, which may appear between bytes?
+fn is_separator(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | b'\r' | b':' | b'-')
+}
+
+/// A lenient, incremental hexadecimal decoder
+///
+/// Unlike `decode_hex`, whitespace, `:` and `-` separators, and `0x` or `\x` prefixes are allowed
+/// between bytes, so hex dumps, MAC addresses and fingerprints can be decoded directly. The input
+/// may be split into blocks anywhere, even in the middle of a byte or a prefix; offsets in errors
+/// are relative to the start of the whole input.
+#[derive(Debug, Default)]
+pub struct HexDecoder {
+    /// The offset of the next byte.
+    offset: u64,
+    /// The first digit of the current byte, and its offset.
+    high: Option<(u8, u64)>,
+    /// A `0` or `\`, which might start a prefix, and its offset.
+    prefix: Option<(u8, u64)>,
+}
+
+impl HexDecoder {
+    /// Create a decoder at the start of the input.
+    pub fn new() -> HexDecoder {
+        HexDecoder::default()
+    }
+
+    /// Decode the next block of the input, appending the bytes to `out`.
+    ///
+    /// On error, the bytes preceding the offending digit have been appended.
+    pub fn decode(&mut self, block: &[u8], out: &mut Vec<u8>) -> Result<()> {
+        let mut n = 0;
+        while n < block.len() {
+            if self.high.is_none() && self.prefix.is_none() {
+                // Decode runs of plain digits at once. A `0` of a prefix is never in an even run.
+                let run = block[n..].iter().take_while(|&&b| try_ascii_to_hex(b).is_some()).count();
+                let run = run - run % 2;
+                if run != 0 {
+                    let start = out.len();
+                    out.resize(start + run / 2, 0);
+                    decode_hex(&block[n..n + run], &mut out[start..])?;
+                    self.offset += run as u64;
+                    n += run;
+                    continue;
+                }
+            }
+
+            let b = block[n];
+            let offset = self.offset;
+            self.offset += 1;
+            n += 1;
+
+            if let Some((p, p_offset)) = self.prefix.take() {
+                if b == b'x' || b == b'X' {
+                    // A `0x` or `\x` prefix, which is skipped.
+                    continue;
+                }
+                // Not a prefix after all, so the `0` is the first digit of the byte.
+                self.high = Some((digit(p, p_offset)?, p_offset));
+            }
+
+            match self.high {
+                // A byte can not be split by a separator.
+                Some((_, h_offset)) if is_separator(b) => {
+                    return Err(Error::UnpairedHexDigit {
+                        offset: h_offset,
+                    });
+                },
+                Some((h, _)) => {
+                    out.push(hex_to_u8((h, digit(b, offset)?)));
+                    self.high = None;
+                },
+                None => match b {
+                    // Whitespace and separators are allowed between bytes.
+                    _ if is_separator(b) => (),
+                    // So are `0x` and `\x` prefixes.
+                    b'0' | b'\\' => self.prefix = Some((b, offset)),
+                    _ => self.high = Some((digit(b, offset)?, offset)),
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that the input did not end in the middle of a byte.
+    pub fn finish(&mut self) -> Result<()> {
+        if let Some((p, p_offset)) = self.prefix.take() {
+            self.high = Some((digit(p, p_offset)?, p_offset));
+        }
+
+        if let Some((_, offset)) = self.high {
+            return Err(Error::UnpairedHexDigit { offset });
+        }
+
+        Ok(())
+    }
+}
+
+/// SIMD implementations of the bulk conversions.
+///
+/// The functions convert as much of the input as they can, and return the number of input bytes
+/// converted. The rest is left to the scalar code.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod simd {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    /// Encode the bytes, using `alpha` as the digit for ten.
+    pub fn encode(src: &[u8], dst: &mut [u8], alpha: u8) -> usize {
+        // The length of `dst` is checked by the caller.
+        unsafe {
+            if is_x86_feature_detected!("avx2") {
+                let n = encode_avx2(src, dst, alpha);
+                n + encode_sse2(&src[n..], &mut dst[n * 2..], alpha)
+            } else if is_x86_feature_detected!("sse2") {
+                encode_sse2(src, dst, alpha)
+            } else {
+                0
+            }
+        }
+    }
+
+    /// Decode the digits, stopping at the first block containing an invalid digit.
+    pub fn decode(src: &[u8], dst: &mut [u8]) -> usize {
+        // The length of `dst` is checked by the caller.
+        unsafe {
+            if is_x86_feature_detected!("avx2") {
+                let n = decode_avx2(src, dst);
+                n + decode_sse2(&src[n..], &mut dst[n / 2..])
+            } else if is_x86_feature_detected!("sse2") {
+                decode_sse2(src, dst)
+            } else {
+                0
+            }
+        }
+    }
+
+    /// Encode 16 bytes at a time.
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn encode_sse2(src: &[u8], dst: &mut [u8], alpha: u8) -> usize {
+        let mask = _mm_set1_epi8(0b1111);
+        let nine = _mm_set1_epi8(9);
+        let zero = _mm_set1_epi8(b'0' as i8);
+        let alpha = _mm_set1_epi8((alpha - b'0' - 10) as i8);
+
+        let mut n = 0;
+        while n + 16 <= src.len() {
+            let x = _mm_loadu_si128(src.as_ptr().add(n) as *const __m128i);
+            let hi = _mm_and_si128(_mm_srli_epi16(x, 4), mask);
+            let lo = _mm_and_si128(x, mask);
+
+            // Interleave the nibbles, and move them to the digits.
+            let halves = [_mm_unpacklo_epi8(hi, lo), _mm_unpackhi_epi8(hi, lo)];
+            for (i, &nibbles) in halves.iter().enumerate() {
+                let above_nine = _mm_and_si128(_mm_cmpgt_epi8(nibbles, nine), alpha);
+                let digits = _mm_add_epi8(_mm_add_epi8(nibbles, zero), above_nine);
+                _mm_storeu_si128(dst.as_mut_ptr().add(n * 2 + i * 16) as *mut __m128i, digits);
+            }
+
+            n += 16;
+        }
+
+        n
+    }
+
+    /// Encode 32 bytes at a time.
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn encode_avx2(src: &[u8], dst: &mut [u8], alpha: u8) -> usize {
+        let mask = _mm256_set1_epi8(0b1111);
+        let nine = _mm256_set1_epi8(9);
+        let zero = _mm256_set1_epi8(b'0' as i8);
+        let alpha = _mm256_set1_epi8((alpha - b'0' - 10) as i8);
+
+        let mut n = 0;
+        while n + 32 <= src.len() {
+            let x = _mm256_loadu_si256(src.as_ptr().add(n) as *const __m256i);
+            let hi = _mm256_and_si256(_mm256_srli_epi16(x, 4), mask);
+            let lo = _mm256_and_si256(x, mask);
+
+            // The unpacking works within the 128-bit lanes, so the lanes must be reordered.
+            let a = _mm256_unpacklo_epi8(hi, lo);
+            let b = _mm256_unpackhi_epi8(hi, lo);
+            for (i, &nibbles) in [_mm256_permute2x128_si256(a, b, 0x20),
+                                  _mm256_permute2x128_si256(a, b, 0x31)].iter().enumerate() {
+                let above_nine = _mm256_and_si256(_mm256_cmpgt_epi8(nibbles, nine), alpha);
+                let digits = _mm256_add_epi8(_mm256_add_epi8(nibbles, zero), above_nine);
+                _mm256_storeu_si256(dst.as_mut_ptr().add(n * 2 + i * 32) as *mut __m256i, digits);
+            }
+
+            n += 32;
+        }
+
+        n
+    }
+
+    /// Decode 32 digits at a time.
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn decode_sse2(src: &[u8], dst: &mut [u8]) -> usize {
+        let mut n = 0;
+        while n + 32 <= src.len() {
+            let (a, a_valid) = values_sse2(_mm_loadu_si128(src.as_ptr().add(n) as *const __m128i));
+            let (b, b_valid) = values_sse2(_mm_loadu_si128(src.as_ptr().add(n + 16)
+                                                           as *const __m128i));
+            if _mm_movemask_epi8(_mm_and_si128(a_valid, b_valid)) != 0xFFFF {
+                break;
+            }
+
+            let bytes = _mm_packus_epi16(pairs_sse2(a), pairs_sse2(b));
+            _mm_storeu_si128(dst.as_mut_ptr().add(n / 2) as *mut __m128i, bytes);
+
+            n += 32;
+        }
+
+        n
+    }
+
+    /// Get the values of the digits, and a mask of the valid digits.
+    #[target_feature(enable = "sse2")]
+    unsafe fn values_sse2(x: __m128i) -> (__m128i, __m128i) {
+        // The comparisons are signed, so bytes above 0x7F are never in the ranges.
+        let digit = _mm_and_si128(_mm_cmpgt_epi8(x, _mm_set1_epi8(b'0' as i8 - 1)),
+                                  _mm_cmplt_epi8(x, _mm_set1_epi8(b'9' as i8 + 1)));
+        let lower = _mm_or_si128(x, _mm_set1_epi8(0x20));
+        let alpha = _mm_and_si128(_mm_cmpgt_epi8(lower, _mm_set1_epi8(b'a' as i8 - 1)),
+                                  _mm_cmplt_epi8(lower, _mm_set1_epi8(b'f' as i8 + 1)));
+
+        let digit_value = _mm_sub_epi8(x, _mm_set1_epi8(b'0' as i8));
+        let alpha_value = _mm_sub_epi8(lower, _mm_set1_epi8(b'a' as i8 - 10));
+        let value = _mm_or_si128(_mm_and_si128(digit, digit_value),
+                                 _mm_and_si128(alpha, alpha_value));
+        (value, _mm_or_si128(digit, alpha))
+    }
+
+    /// Combine every pair of digit values into a byte, in the low half of the 16-bit lanes.
+    #[target_feature(enable = "sse2")]
+    unsafe fn pairs_sse2(x: __m128i) -> __m128i {
+        _mm_and_si128(_mm_or_si128(_mm_slli_epi16(x, 4), _mm_srli_epi16(x, 8)),
+                      _mm_set1_epi16(0xFF))
+    }
+
+    /// Decode 64 digits at a time.
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn decode_avx2(src: &[u8], dst: &mut [u8]) -> usize {
+        let mut n = 0;
+        while n + 64 <= src.len() {
+            let (a, a_valid) = values_avx2(_mm256_loadu_si256(src.as_ptr().add(n)
+                                                              as *const __m256i));
+            let (b, b_valid) = values_avx2(_mm256_loadu_si256(src.as_ptr().add(n + 32)
+                                                              as *const __m256i));
+            if _mm256_movemask_epi8(_mm256_and_si256(a_valid, b_valid)) != -1 {
+                break;
+            }
+
+            // The packing works within the 128-bit lanes, so the quarters must be reordered.
+            let bytes = _mm256_packus_epi16(pairs_avx2(a), pairs_avx2(b));
+            let bytes = _mm256_permute4x64_epi64(bytes, 0b11_01_10_00);
+            _mm256_storeu_si256(dst.as_mut_ptr().add(n / 2) as *mut __m256i, bytes);
+
+            n += 64;
+        }
+
+        n
+    }
+
+    /// Get the values of the digits, and a mask of the valid digits.
+    #[target_feature(enable = "avx2")]
+    unsafe fn values_avx2(x: __m256i) -> (__m256i, __m256i) {
+        let digit = _mm256_andnot_si256(_mm256_cmpgt_epi8(x, _mm256_set1_epi8(b'9' as i8)),
+                                        _mm256_cmpgt_epi8(x, _mm256_set1_epi8(b'0' as i8 - 1)));
+        let lower = _mm256_or_si256(x, _mm256_set1_epi8(0x20));
+        let alpha = _mm256_andnot_si256(_mm256_cmpgt_epi8(lower, _mm256_set1_epi8(b'f' as i8)),
+                                        _mm256_cmpgt_epi8(lower,
+                                                          _mm256_set1_epi8(b'a' as i8 - 1)));
+
+        let digit_value = _mm256_sub_epi8(x, _mm256_set1_epi8(b'0' as i8));
+        let alpha_value = _mm256_sub_epi8(lower, _mm256_set1_epi8(b'a' as i8 - 10));
+        let value = _mm256_or_si256(_mm256_and_si256(digit, digit_value),
+                                    _mm256_and_si256(alpha, alpha_value));
+        (value, _mm256_or_si256(digit, alpha))
+    }
+
+    /// Combine every pair of digit values into a byte, in the low half of the 16-bit lanes.
+    #[target_feature(enable = "avx2")]
+    unsafe fn pairs_avx2(x: __m256i) -> __m256i {
+        _mm256_and_si256(_mm256_or_si256(_mm256_slli_epi16(x, 4), _mm256_srli_epi16(x, 8)),
+                         _mm256_set1_epi16(0xFF))
+    }
+}
+
+/// The fallback for architectures without SIMD implementations, which converts nothing.
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+mod simd {
+    pub fn encode(_src: &[u8], _dst: &mut [u8], _alpha: u8) -> usize {
+        0
+    }
+
+    pub fn decode(_src: &[u8], _dst: &mut [u8]) -> usize {
+        0
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -71,6 +617,14 @@ mod tests {
         assert_eq!(hex_to_ascii(0xc), b'c');
     }
 
+    #[test]
+    fn test_hex2ascii_upper() {
+        for i in 0..16 {
+            assert_eq!(hex_to_ascii_upper(i), hex_to_ascii(i).to_ascii_uppercase());
+            assert_eq!(try_ascii_to_hex(hex_to_ascii_upper(i)), Some(i));
+        }
+    }
+
     #[test]
     fn ascii2hex() {
         for i in 0..16 {
@@ -78,6 +632,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn try_ascii2hex() {
+        for i in 0..16 {
+            assert_eq!(try_ascii_to_hex(hex_to_ascii(i)), Some(i));
+        }
+
+        assert_eq!(try_ascii_to_hex(b'A'), Some(0xA));
+        assert_eq!(try_ascii_to_hex(b'F'), Some(0xF));
+        assert_eq!(try_ascii_to_hex(b'g'), None);
+        assert_eq!(try_ascii_to_hex(b'G'), None);
+        assert_eq!(try_ascii_to_hex(b' '), None);
+        assert_eq!(try_ascii_to_hex(b'/'), None);
+        assert_eq!(try_ascii_to_hex(b':'), None);
+        assert_eq!(try_ascii_to_hex(b'@'), None);
+        assert_eq!(try_ascii_to_hex(b'`'), None);
+        assert_eq!(try_ascii_to_hex(0xFF), None);
+    }
+
     #[test]
     fn test_hex() {
         for i in 0..16 {
@@ -113,4 +685,251 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn u64_byte() {
+        assert_eq!(u64_byte_array(0x0123456789ABCDEF),
+                   [0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF]);
+        for i in 0..255923 {
+            assert_eq!(u64_byte_array(i)[4..], u32_byte_array(i as u32));
+            assert_eq!(u64_byte_array(i << 32)[..4], u32_byte_array(i as u32));
+        }
+    }
+
+    /// Some bytes of every value, in a jumbled order.
+    fn jumbled(len: usize) -> Vec<u8> {
+        (0..len).map(|n| (n * 167 + n / 256) as u8).collect()
+    }
+
+    /// Encode with the per-byte helpers.
+    fn naive_hex(src: &[u8]) -> Vec<u8> {
+        let mut hex = Vec::new();
+        for &b in src {
+            let (h1, h2) = u8_to_hex(b);
+            hex.push(hex_to_ascii(h1));
+            hex.push(hex_to_ascii(h2));
+        }
+        hex
+    }
+
+    #[test]
+    fn bulk_encode() {
+        // The lengths cover the SIMD blocks as well as the scalar remainder.
+        for len in 0..300 {
+            let src = jumbled(len);
+            let mut hex = vec![0; len * 2];
+            encode_hex(&src, &mut hex);
+            assert_eq!(hex, naive_hex(&src));
+
+            encode_hex_upper(&src, &mut hex);
+            assert_eq!(hex, naive_hex(&src).to_ascii_uppercase());
+        }
+    }
+
+    #[test]
+    fn bulk_decode() {
+        for len in 0..300 {
+            let src = jumbled(len);
+            let mut bytes = vec![0; len];
+
+            let hex = naive_hex(&src);
+            decode_hex(&hex, &mut bytes).unwrap();
+            assert_eq!(bytes, src);
+
+            decode_hex(&hex.to_ascii_uppercase(), &mut bytes).unwrap();
+            assert_eq!(bytes, src);
+        }
+    }
+
+    #[test]
+    fn bulk_decode_errors() {
+        let mut bytes = [0; 100];
+        for offset in 0..200 {
+            for &byte in b"gG/:@`x \xFF\x80".iter() {
+                let mut hex = naive_hex(&jumbled(100));
+                hex[offset] = byte;
+                match decode_hex(&hex, &mut bytes) {
+                    Err(Error::InvalidHexDigit { offset: o, byte: b }) => {
+                        assert_eq!((o, b), (offset as u64, byte));
+                        assert_eq!(bytes[..offset / 2], jumbled(100)[..offset / 2]);
+                    },
+                    res => panic!("unexpected result {:?}", res),
+                }
+            }
+        }
+
+        match decode_hex(b"abc", &mut bytes) {
+            Err(Error::UnpairedHexDigit { offset: 2 }) => (),
+            res => panic!("unexpected result {:?}", res),
+        }
+        match decode_hex(b"abx", &mut bytes) {
+            Err(Error::InvalidHexDigit { offset: 2, byte: b'x' }) => (),
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+
+    /// Encode `src` with the layout, in blocks of `block` bytes.
+    fn encode_layout(src: &[u8], layout: &HexLayout, block: usize) -> String {
+        let mut encoder = HexEncoder::new(layout.clone());
+        let mut out = Vec::new();
+        for chunk in src.chunks(block) {
+            encoder.encode(chunk, &mut out);
+        }
+        encoder.finish(&mut out);
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn encoder_layout() {
+        let src = b"\x01\x23\x45\x67\x89\xab\xcd";
+        assert_eq!(encode_layout(src, &HexLayout::default(), 3), "0123456789abcd\n");
+        assert_eq!(encode_layout(b"", &HexLayout::default(), 3), "");
+
+        let upper = HexLayout { upper: true, ..HexLayout::default() };
+        assert_eq!(encode_layout(src, &upper, 3), "0123456789ABCD\n");
+
+        let grouped = HexLayout { group: Some(2), separator: ":".to_owned(), ..upper };
+        assert_eq!(encode_layout(src, &grouped, 3), "0123:4567:89AB:CD\n");
+
+        // Lines are broken between bytes, and the separator is dropped at the break.
+        let wrapped = HexLayout { width: Some(10), ..grouped };
+        assert_eq!(encode_layout(src, &wrapped, 3), "0123:4567\n89AB:CD\n");
+        let wrapped = HexLayout { width: Some(5), group: None, ..wrapped };
+        assert_eq!(encode_layout(src, &wrapped, 3), "0123\n4567\n89AB\nCD\n");
+        // A line holds at least one byte.
+        let wrapped = HexLayout { width: Some(1), ..wrapped };
+        assert_eq!(encode_layout(&src[..2], &wrapped, 3), "01\n23\n");
+
+        assert_eq!(encode_layout(b"", &wrapped, 3), "");
+    }
+
+    #[test]
+    fn encoder_blocks() {
+        // The position on the line is kept between blocks.
+        let src = jumbled(1000);
+        let layouts = [
+            HexLayout::default(),
+            HexLayout { width: Some(60), ..HexLayout::default() },
+            HexLayout { group: Some(3), separator: ", ".to_owned(), ..HexLayout::default() },
+            HexLayout { width: Some(47), group: Some(4), upper: true, ..HexLayout::default() },
+        ];
+
+        for layout in layouts.iter() {
+            let whole = encode_layout(&src, layout, src.len());
+            for &block in [1, 2, 3, 7, 64, 999].iter() {
+                assert_eq!(encode_layout(&src, layout, block), whole);
+            }
+        }
+    }
+
+    /// Decode `src` leniently, in blocks of `block` bytes.
+    fn lenient(src: &[u8], block: usize) -> Result<Vec<u8>> {
+        let mut decoder = HexDecoder::new();
+        let mut out = Vec::new();
+        for chunk in src.chunks(block) {
+            decoder.decode(chunk, &mut out)?;
+        }
+        decoder.finish()?;
+        Ok(out)
+    }
+
+    /// Decode `src` leniently, split into blocks of every possible size.
+    fn lenient_split(src: &[u8]) -> Result<Vec<u8>> {
+        let res = lenient(src, src.len() + 1);
+        for block in 1..src.len() + 1 {
+            assert_eq!(format!("{:?}", lenient(src, block)), format!("{:?}", res));
+        }
+        res
+    }
+
+    #[test]
+    fn lenient_prefixes() {
+        assert_eq!(lenient_split(b"0x12\\x340X56\\XaB").unwrap(), [0x12, 0x34, 0x56, 0xab]);
+        assert_eq!(lenient_split(b"0x00 0x0a 00 0a").unwrap(), [0x00, 0x0a, 0x00, 0x0a]);
+        assert_eq!(lenient_split(b"0xx1").unwrap_err().to_string(),
+                   "invalid hexadecimal digit 'x' at offset 2");
+        match lenient_split(b"12 0") {
+            Err(Error::UnpairedHexDigit { offset: 3 }) => (),
+            res => panic!("unexpected result {:?}", res),
+        }
+        match lenient_split(b"12 \\") {
+            Err(Error::InvalidHexDigit { offset: 3, byte: b'\\' }) => (),
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+
+    #[test]
+    fn lenient_separators() {
+        for &sep in b" \t\r\n:-".iter() {
+            let src = [b'a', b'b', sep, b'c', b'd', sep, sep, b'0', b'1', sep];
+            assert_eq!(lenient_split(&src).unwrap(), [0xab, 0xcd, 0x01]);
+
+            // A byte can not be split by a separator.
+            match lenient_split(&[b'a', b'b', b'c', sep, b'd']) {
+                Err(Error::UnpairedHexDigit { offset: 2 }) => (),
+                res => panic!("unexpected result {:?}", res),
+            }
+        }
+        assert_eq!(lenient_split(b"de:ad-be ef\r\n").unwrap(), [0xde, 0xad, 0xbe, 0xef]);
+    }
+
+    #[test]
+    fn lenient_errors() {
+        match lenient_split(b"abc") {
+            Err(Error::UnpairedHexDigit { offset: 2 }) => (),
+            res => panic!("unexpected result {:?}", res),
+        }
+        match lenient_split(b"ab cd e") {
+            Err(Error::UnpairedHexDigit { offset: 6 }) => (),
+            res => panic!("unexpected result {:?}", res),
+        }
+        match lenient_split(b"ab cd eg") {
+            Err(Error::InvalidHexDigit { offset: 7, byte: b'g' }) => (),
+            res => panic!("unexpected result {:?}", res),
+        }
+
+        // The bytes preceding the error are kept.
+        let mut decoder = HexDecoder::new();
+        let mut out = Vec::new();
+        assert!(decoder.decode(b"0102 03z4", &mut out).is_err());
+        assert_eq!(out, [1, 2, 3]);
+    }
+
+    #[test]
+    fn lenient_blocks() {
+        // The size of the blocks read by 'hex --decode'.
+        const BLOCK: usize = 64 * 1024;
+
+        // The leading space puts every block boundary in the middle of a byte.
+        let mut src = vec![b' '];
+        src.extend(naive_hex(&jumbled(BLOCK)));
+        assert_eq!(lenient(&src, BLOCK).unwrap(), jumbled(BLOCK));
+
+        src.extend_from_slice(b" \\xf");
+        match lenient(&src, BLOCK) {
+            Err(Error::UnpairedHexDigit { offset }) => assert_eq!(offset, BLOCK as u64 * 2 + 4),
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[test]
+    fn bulk_sse2() {
+        // The SSE2 code is only used for the remainder when AVX2 is available, so test it alone.
+        if !is_x86_feature_detected!("sse2") {
+            return;
+        }
+
+        let src = jumbled(256);
+        let mut hex = vec![0; 512];
+        assert_eq!(unsafe { simd::encode_sse2(&src, &mut hex, b'a') }, 256);
+        assert_eq!(hex, naive_hex(&src));
+
+        let mut bytes = vec![0; 256];
+        assert_eq!(unsafe { simd::decode_sse2(&hex, &mut bytes) }, 512);
+        assert_eq!(bytes, src);
+
+        hex[100] = b'g';
+        assert_eq!(unsafe { simd::decode_sse2(&hex, &mut bytes) }, 96);
+    }
 }
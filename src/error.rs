@@ -0,0 +1,141 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::result;
+
+/// A specialized `Result` type for the binutils.
+pub type Result<T> = result::Result<T, Error>;
+
+/// An error encountered while processing a byte stream.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing a stream failed.
+    Io(io::Error),
+    /// A byte which is not a hexadecimal digit was found where one was expected.
+    InvalidHexDigit {
+        /// The offset of the byte in the input stream.
+        offset: u64,
+        /// The offending byte.
+        byte: u8,
+    },
+    /// A byte which is not a binary digit was found where one was expected.
+    InvalidBinaryDigit {
+        /// The offset of the byte in the input stream.
+        offset: u64,
+        /// The offending byte.
+        byte: u8,
+    },
+    /// A hexadecimal digit was left without the second digit of its byte.
+    UnpairedHexDigit {
+        /// The offset of the digit in the input stream.
+        offset: u64,
+    },
+    /// A line of a dump could not be parsed.
+    MalformedLine {
+        /// The line number, starting at 1.
+        line: u64,
+        /// What is wrong with the line.
+        reason: &'static str,
+    },
+    /// A format string could not be parsed.
+    InvalidFormat {
+        /// The offset of the offending character in the format string.
+        offset: usize,
+        /// What is wrong with the format string.
+        reason: &'static str,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref err) => write!(f, "{}", err),
+            Error::InvalidHexDigit { offset, byte } => {
+                write!(f, "invalid hexadecimal digit {:?} at offset {}", byte as char, offset)
+            },
+            Error::InvalidBinaryDigit { offset, byte } => {
+                write!(f, "invalid binary digit {:?} at offset {}", byte as char, offset)
+            },
+            Error::UnpairedHexDigit { offset } => {
+                write!(f, "unpaired hexadecimal digit at offset {}", offset)
+            },
+            Error::MalformedLine { line, reason } => {
+                write!(f, "malformed line {}: {}", line, reason)
+            },
+            Error::InvalidFormat { offset, reason } => {
+                write!(f, "invalid format string at offset {}: {}", offset, reason)
+            },
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::Io(_) => "I/O error",
+            Error::InvalidHexDigit { .. } => "invalid hexadecimal digit",
+            Error::InvalidBinaryDigit { .. } => "invalid binary digit",
+            Error::UnpairedHexDigit { .. } => "unpaired hexadecimal digit",
+            Error::MalformedLine { .. } => "malformed line",
+            Error::InvalidFormat { .. } => "invalid format string",
+        }
+    }
+
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error as StdError;
+
+    #[test]
+    fn display() {
+        let err = Error::InvalidHexDigit { offset: 12, byte: b'g' };
+        assert_eq!(err.to_string(), "invalid hexadecimal digit 'g' at offset 12");
+        let err = Error::InvalidBinaryDigit { offset: 3, byte: b'2' };
+        assert_eq!(err.to_string(), "invalid binary digit '2' at offset 3");
+        let err = Error::UnpairedHexDigit { offset: 7 };
+        assert_eq!(err.to_string(), "unpaired hexadecimal digit at offset 7");
+        let err = Error::MalformedLine { line: 2, reason: "missing address" };
+        assert_eq!(err.to_string(), "malformed line 2: missing address");
+        let err = Error::InvalidFormat { offset: 1, reason: "unknown conversion" };
+        assert_eq!(err.to_string(), "invalid format string at offset 1: unknown conversion");
+    }
+
+    #[test]
+    fn io() {
+        let err: Error = io::Error::new(io::ErrorKind::UnexpectedEof, "early end").into();
+        assert_eq!(err.to_string(), "early end");
+        match err.source().and_then(|e| e.downcast_ref::<io::Error>()) {
+            Some(e) => assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof),
+            None => panic!("the I/O error is not the source"),
+        }
+
+        assert!(Error::UnpairedHexDigit { offset: 0 }.source().is_none());
+    }
+
+    #[test]
+    fn propagate() {
+        fn read() -> Result<()> {
+            Err(io::Error::other("broken"))?;
+            Ok(())
+        }
+
+        match read() {
+            Err(Error::Io(ref e)) if e.kind() == io::ErrorKind::Other => (),
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+}
@@ -0,0 +1,882 @@
+use std::cmp;
+use std::io::{self, Write};
+use std::str;
+
+use error::{Error, Result};
+use lines::Lines;
+use strings::{IsPrintable, Radix};
+use text;
+
+/// The flags of a conversion, like those of `printf`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Flags {
+    /// Left-justify the value in its field ('-').
+    pub left: bool,
+    /// Always show the sign of a number ('+').
+    pub plus: bool,
+    /// Show a space in place of the sign of a positive number (' ').
+    pub space: bool,
+    /// Use the alternate form ('#').
+    pub alternate: bool,
+    /// Pad numbers with zeros rather than spaces ('0').
+    pub zero: bool,
+}
+
+/// What a conversion shows.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// A signed decimal integer ('d' and 'i').
+    Signed,
+    /// An unsigned decimal integer ('u').
+    Unsigned,
+    /// An octal integer ('o').
+    Octal,
+    /// A hexadecimal integer, with uppercase digits when set ('x' and 'X').
+    Hex(bool),
+    /// A floating point number, in the style given by the conversion character ('e', 'E', 'f',
+    /// 'g' and 'G').
+    Float(u8),
+    /// A byte, as is ('c').
+    Char,
+    /// A string of bytes, ending at the first NUL byte ('s').
+    String,
+    /// The address of the next byte ('_a').
+    Address(Radix),
+    /// The address following the end of the input ('_A'). A unit containing it is only shown
+    /// once, when all input is processed.
+    EndAddress(Radix),
+    /// A character, with C escapes or octal for the non-printable ones ('_c').
+    Escaped,
+    /// A printable character, or `.` for the non-printable ones ('_p').
+    Printable,
+    /// An ASCII character, with names for the control characters and hexadecimal for the bytes
+    /// above 0x7F ('_u').
+    Named,
+}
+
+impl Kind {
+    /// Is this conversion shown in place of the input bytes, as opposed to an address?
+    #[inline]
+    fn consumes(self) -> bool {
+        !matches!(self, Kind::Address(_) | Kind::EndAddress(_))
+    }
+}
+
+/// A conversion of a format string, like `%02x`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Conversion {
+    /// The flags.
+    pub flags: Flags,
+    /// The minimum width of the field.
+    pub width: Option<usize>,
+    /// The precision, whose meaning depends on the kind of conversion, like in `printf`.
+    pub precision: Option<usize>,
+    /// What is shown.
+    pub kind: Kind,
+    /// The number of bytes shown, which is zero for addresses.
+    pub size: usize,
+}
+
+/// An element of a format string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Item {
+    /// Text, which is written as is.
+    Text(Vec<u8>),
+    /// A conversion of the input.
+    Conversion(Conversion),
+}
+
+/// The largest number of bytes a format may consume, and the largest width or precision of a
+/// conversion.
+pub const MAX_BLOCK_SIZE: usize = 1024 * 1024;
+
+/// A format unit: a format string applied a number of times.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Unit {
+    /// The number of times the format string is applied.
+    pub iterations: usize,
+    /// Was the iteration count given? Otherwise the last unit of a format is repeated to fill the
+    /// block.
+    pub fixed: bool,
+    /// The number of bytes consumed by every iteration.
+    pub bytes: usize,
+    /// The elements of the format string.
+    pub items: Vec<Item>,
+}
+
+impl Unit {
+    /// Is this unit shown at the end of the input, rather than for every block?
+    fn is_end(&self) -> bool {
+        self.items.iter().any(|i| {
+            matches!(*i, Item::Conversion(Conversion { kind: Kind::EndAddress(_), .. }))
+        })
+    }
+}
+
+/// A format, that is, a sequence of format units, as given to '-e' of BSD hexdump.
+///
+/// Every format processes the same blocks of input, so that multiple formats show the same bytes
+/// in different ways.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Format {
+    /// The format units.
+    pub units: Vec<Unit>,
+}
+
+/// Skip whitespace, returning the new position.
+fn skip_whitespace(s: &[u8], mut n: usize) -> usize {
+    while n < s.len() && (s[n] as char).is_whitespace() {
+        n += 1;
+    }
+    n
+}
+
+/// Parse a decimal number, returning it and the new position.
+///
+/// A number too large for a `usize` saturates, so that it is caught by the limits.
+fn parse_number(s: &[u8], n: usize) -> Option<(usize, usize)> {
+    let digits = s[n..].iter().take_while(|b| b.is_ascii_digit()).count();
+    if digits == 0 {
+        return None;
+    }
+
+    let d = str::from_utf8(&s[n..n + digits]).ok()
+        .and_then(|d| d.parse().ok())
+        .unwrap_or(usize::MAX);
+    Some((d, n + digits))
+}
+
+/// Parse a conversion, following the `%` at `start`, returning it and the new position.
+fn parse_conversion(s: &[u8], start: usize) -> Result<(Conversion, usize)> {
+    let err = |offset, reason| Error::InvalidFormat {
+        offset,
+        reason,
+    };
+    let mut n = start + 1;
+
+    let mut flags = Flags::default();
+    loop {
+        match s.get(n) {
+            Some(&b'-') => flags.left = true,
+            Some(&b'+') => flags.plus = true,
+            Some(&b' ') => flags.space = true,
+            Some(&b'#') => flags.alternate = true,
+            Some(&b'0') => flags.zero = true,
+            _ => break,
+        }
+        n += 1;
+    }
+
+    let width = parse_number(s, n).map(|(w, m)| {
+        n = m;
+        w
+    });
+    let precision = if s.get(n) == Some(&b'.') {
+        // A lone '.' means a precision of zero, like in `printf`.
+        let (p, m) = parse_number(s, n + 1).unwrap_or((0, n + 1));
+        n = m;
+        Some(p)
+    } else {
+        None
+    };
+    let too_large = |x: Option<usize>| x.is_some_and(|x| x > MAX_BLOCK_SIZE);
+    if too_large(width) || too_large(precision) {
+        return Err(err(start, "the width or precision is too large"));
+    }
+
+    let radix = |b: Option<&u8>| match b {
+        Some(&b'd') => Ok(Radix::Decimal),
+        Some(&b'o') => Ok(Radix::Octal),
+        Some(&b'x') => Ok(Radix::Hexadecimal),
+        _ => Err(err(n + 2, "expected the address radix 'd', 'o' or 'x'")),
+    };
+    let (kind, size, len) = match s.get(n) {
+        Some(&b'd') | Some(&b'i') => (Kind::Signed, 4, 1),
+        Some(&b'u') => (Kind::Unsigned, 4, 1),
+        Some(&b'o') => (Kind::Octal, 4, 1),
+        Some(&b'x') => (Kind::Hex(false), 4, 1),
+        Some(&b'X') => (Kind::Hex(true), 4, 1),
+        Some(&c @ b'e') | Some(&c @ b'E') | Some(&c @ b'f') | Some(&c @ b'g') | Some(&c @ b'G') => {
+            (Kind::Float(c), 8, 1)
+        },
+        Some(&b'c') => (Kind::Char, 1, 1),
+        // The size of a string is given by the precision, or the byte count of the unit.
+        Some(&b's') => (Kind::String, precision.unwrap_or(0), 1),
+        Some(&b'_') => match s.get(n + 1) {
+            Some(&b'a') => (Kind::Address(radix(s.get(n + 2))?), 0, 3),
+            Some(&b'A') => (Kind::EndAddress(radix(s.get(n + 2))?), 0, 3),
+            Some(&b'c') => (Kind::Escaped, 1, 2),
+            Some(&b'p') => (Kind::Printable, 1, 2),
+            Some(&b'u') => (Kind::Named, 1, 2),
+            _ => return Err(err(n + 1, "unknown conversion")),
+        },
+        Some(&b'*') => return Err(err(n, "variable widths and precisions are not supported")),
+        Some(_) => return Err(err(n, "unknown conversion")),
+        None => return Err(err(n, "unterminated conversion")),
+    };
+
+    Ok((Conversion {
+        flags,
+        width,
+        precision,
+        kind,
+        size,
+    }, n + len))
+}
+
+/// Parse a quoted format string, starting at the quote at `start`, returning its elements and the
+/// new position.
+fn parse_string(s: &[u8], start: usize) -> Result<(Vec<Item>, usize)> {
+    let mut items = Vec::new();
+    let mut text = Vec::new();
+    let mut n = start + 1;
+
+    loop {
+        match s.get(n) {
+            None => {
+                return Err(Error::InvalidFormat {
+                    offset: start,
+                    reason: "unterminated format string",
+                });
+            },
+            Some(&b'"') => break,
+            Some(&b'\\') => {
+                text.push(match s.get(n + 1) {
+                    Some(&b'a') => 7,
+                    Some(&b'b') => 8,
+                    Some(&b'f') => 0x0C,
+                    Some(&b'n') => b'\n',
+                    Some(&b'r') => b'\r',
+                    Some(&b't') => b'\t',
+                    Some(&b'v') => 0x0B,
+                    Some(&b'0') => 0,
+                    Some(&b) => b,
+                    None => b'\\',
+                });
+                n += 2;
+            },
+            Some(&b'%') if s.get(n + 1) == Some(&b'%') => {
+                text.push(b'%');
+                n += 2;
+            },
+            Some(&b'%') => {
+                if !text.is_empty() {
+                    items.push(Item::Text(text.split_off(0)));
+                }
+                let (conversion, m) = parse_conversion(s, n)?;
+                items.push(Item::Conversion(conversion));
+                n = m;
+            },
+            Some(&b) => {
+                text.push(b);
+                n += 1;
+            },
+        }
+    }
+
+    if !text.is_empty() {
+        items.push(Item::Text(text));
+    }
+
+    Ok((items, n + 1))
+}
+
+impl Format {
+    /// Parse a format, as given to '-e' of BSD hexdump.
+    ///
+    /// A format consists of format units, separated by whitespace. Every unit is a quoted format
+    /// string with `printf` style conversions, optionally preceded by an iteration count and a
+    /// byte count: `[ITERATIONS][/BYTES] "FORMAT"`.
+    pub fn parse(format: &str) -> Result<Format> {
+        let s = format.as_bytes();
+        let err = |offset, reason| Error::InvalidFormat {
+            offset,
+            reason,
+        };
+        let mut units = Vec::new();
+        // The number of bytes consumed by the units so far.
+        let mut consumed = 0;
+        let mut n = skip_whitespace(s, 0);
+
+        while n < s.len() {
+            let iterations = parse_number(s, n).map(|(i, m)| {
+                n = skip_whitespace(s, m);
+                i
+            });
+            let byte_count = if s.get(n) == Some(&b'/') {
+                let (b, m) = parse_number(s, skip_whitespace(s, n + 1))
+                    .ok_or(err(n + 1, "expected a byte count"))?;
+                n = skip_whitespace(s, m);
+                Some(b)
+            } else {
+                None
+            };
+            if iterations == Some(0) || byte_count == Some(0) {
+                return Err(err(n, "the counts must be positive"));
+            }
+
+            if s.get(n) != Some(&b'"') {
+                return Err(err(n, "expected a quoted format string"));
+            }
+            let start = n;
+            let (mut items, m) = parse_string(s, n)?;
+            n = skip_whitespace(s, m);
+
+            // The byte count applies to the only conversion, which shows input.
+            let mut conversions = items.iter_mut().filter_map(|i| match *i {
+                Item::Conversion(ref mut c) if c.kind.consumes() => Some(c),
+                _ => None,
+            }).collect::<Vec<_>>();
+            if let Some(bytes) = byte_count {
+                if conversions.len() > 1 {
+                    return Err(err(start,
+                                   "a byte count can only be given for a single conversion"));
+                }
+                if let Some(c) = conversions.first_mut() {
+                    c.size = bytes;
+                }
+            }
+            for c in &conversions {
+                let valid = match c.kind {
+                    Kind::Signed | Kind::Unsigned | Kind::Octal | Kind::Hex(_) => {
+                        c.size == 1 || c.size == 2 || c.size == 4 || c.size == 8
+                    },
+                    Kind::Float(_) => c.size == 4 || c.size == 8,
+                    Kind::String => c.size != 0,
+                    _ => c.size == 1,
+                };
+                if !valid {
+                    return Err(err(start, match c.kind {
+                        Kind::String => "a string needs a precision or a byte count",
+                        _ => "invalid byte count for the conversion",
+                    }));
+                }
+            }
+
+            let bytes = match byte_count {
+                Some(b) => Some(b),
+                None => conversions.iter().try_fold(0, |sum: usize, c| sum.checked_add(c.size)),
+            };
+            let total = bytes.and_then(|b| b.checked_mul(iterations.unwrap_or(1)))
+                .and_then(|b| b.checked_add(consumed));
+            consumed = match total {
+                Some(total) if total <= MAX_BLOCK_SIZE => total,
+                _ => return Err(err(start, "the format consumes too many bytes")),
+            };
+
+            let bytes = bytes.unwrap_or(0);
+            units.push(Unit {
+                iterations: iterations.unwrap_or(1),
+                fixed: iterations.is_some(),
+                bytes,
+                items,
+            });
+        }
+
+        if units.is_empty() {
+            return Err(err(0, "empty format"));
+        }
+
+        Ok(Format {
+            units,
+        })
+    }
+
+    /// Parse a format file, as given to '-f' of BSD hexdump.
+    ///
+    /// Every line holds a format, and empty lines and lines starting with `#` are ignored. The
+    /// offsets of errors are those in the whole file.
+    pub fn parse_file(file: &str) -> Result<Vec<Format>> {
+        let mut formats = Vec::new();
+        let mut offset = 0;
+
+        for line in file.split('\n') {
+            let trimmed = line.trim();
+            if !trimmed.is_empty() && !trimmed.starts_with('#') {
+                formats.push(Format::parse(line).map_err(|err| match err {
+                    Error::InvalidFormat { offset: n, reason } => Error::InvalidFormat {
+                        offset: offset + n,
+                        reason,
+                    },
+                    err => err,
+                })?);
+            }
+            offset += line.len() + 1;
+        }
+
+        Ok(formats)
+    }
+
+    /// The formats of the canonical hex+ASCII display, '-C' of BSD hexdump.
+    pub fn canonical() -> Vec<Format> {
+        [
+            r#""%08.8_Ax\n""#,
+            r#""%08.8_ax  " 8/1 "%02x " "  " 8/1 "%02x ""#,
+            r#""  |" 16/1 "%_p" "|\n""#,
+        ].iter().map(|f| Format::parse(f).unwrap()).collect()
+    }
+
+    /// The number of bytes consumed by the format.
+    ///
+    /// This saturates, rather than overflowing, for a format which is built by hand. A parsed
+    /// format consumes at most `MAX_BLOCK_SIZE` bytes.
+    pub fn bytes(&self) -> usize {
+        self.units.iter().fold(0, |sum, u| sum.saturating_add(u.iterations.saturating_mul(u.bytes)))
+    }
+}
+
+/// Write the body of a field, padded to its width.
+fn pad(out: &mut Vec<u8>, flags: Flags, width: Option<usize>, body: &[u8]) {
+    text::pad(out, flags.left, width.unwrap_or(0), body);
+}
+
+/// Write a number, given its sign, prefix and digits, padded to its width.
+fn pad_number(out: &mut Vec<u8>, c: &Conversion, sign: &str, digits: &str, zeros: bool) {
+    let mut body = String::with_capacity(cmp::max(c.width.unwrap_or(0), digits.len() + 2));
+    body.push_str(sign);
+    if zeros && c.flags.zero && !c.flags.left {
+        // The zeros go between the sign and the digits.
+        let fill = c.width.unwrap_or(0).saturating_sub(sign.len() + digits.len());
+        body.extend((0..fill).map(|_| '0'));
+    }
+    body.push_str(digits);
+    pad(out, c.flags, c.width, body.as_bytes());
+}
+
+impl Conversion {
+    /// Write the conversion of the bytes, which are padded by zeros if the input ended.
+    fn write(&self, bytes: &[u8], address: u64, out: &mut Vec<u8>) {
+        // Integers are read in the native byte order.
+        let mut buf = [0; 8];
+        let len = cmp::min(bytes.len(), 8);
+        if cfg!(target_endian = "little") {
+            buf[..len].copy_from_slice(&bytes[..len]);
+        } else if self.size <= 8 {
+            buf[8 - self.size..8 - self.size + len].copy_from_slice(&bytes[..len]);
+        }
+        let value = u64::from_ne_bytes(buf);
+        let bits = cmp::min(self.size, 8) as u32 * 8;
+
+        let sign = |negative: bool| if negative {
+            "-"
+        } else if self.flags.plus {
+            "+"
+        } else if self.flags.space {
+            " "
+        } else {
+            ""
+        };
+        let integer = |out: &mut Vec<u8>, sign: &str, value: u64, radix: Radix, upper: bool| {
+            let mut digits = match radix {
+                Radix::Decimal => format!("{}", value),
+                Radix::Octal => format!("{:o}", value),
+                Radix::Hexadecimal if upper => format!("{:X}", value),
+                Radix::Hexadecimal => format!("{:x}", value),
+            };
+            if self.precision == Some(0) && value == 0 {
+                digits.clear();
+            }
+            if let Some(p) = self.precision {
+                while digits.len() < p {
+                    digits.insert(0, '0');
+                }
+            }
+
+            let mut prefix = sign.to_owned();
+            if self.flags.alternate {
+                match radix {
+                    Radix::Octal if !digits.starts_with('0') => digits.insert(0, '0'),
+                    Radix::Hexadecimal if value != 0 => {
+                        prefix.push_str(if upper { "0X" } else { "0x" })
+                    },
+                    _ => (),
+                }
+            }
+            // Zero padding is disabled by a precision.
+            pad_number(out, self, &prefix, &digits, self.precision.is_none());
+        };
+
+        match self.kind {
+            Kind::Signed => {
+                // Sign extend the value.
+                let shift = 64 - bits;
+                let value = ((value << shift) as i64) >> shift;
+                integer(out, sign(value < 0), value.wrapping_abs() as u64, Radix::Decimal, false)
+            },
+            Kind::Unsigned => integer(out, "", value, Radix::Decimal, false),
+            Kind::Octal => integer(out, "", value, Radix::Octal, false),
+            Kind::Hex(upper) => integer(out, "", value, Radix::Hexadecimal, upper),
+            Kind::Address(radix) | Kind::EndAddress(radix) => {
+                integer(out, "", address, radix, false)
+            },
+            Kind::Float(style) => {
+                let x = if self.size == 4 {
+                    f32::from_bits(value as u32) as f64
+                } else {
+                    f64::from_bits(value)
+                };
+                let body = text::printf(x.abs(), style, self.precision.unwrap_or(6),
+                                        self.flags.alternate);
+                pad_number(out, self, sign(x.is_sign_negative() && !x.is_nan()), &body,
+                           x.is_finite());
+            },
+            Kind::Char => pad(out, self.flags, self.width, &bytes[..1]),
+            Kind::String => {
+                let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+                let len = self.precision.map_or(len, |p| cmp::min(p, len));
+                pad(out, self.flags, self.width, &bytes[..len]);
+            },
+            Kind::Escaped => {
+                let mut escape = Vec::with_capacity(3);
+                text::escape(bytes[0], &mut escape);
+                pad(out, self.flags, self.width, &escape);
+            },
+            Kind::Printable => {
+                let b = bytes[0];
+                pad(out, self.flags, self.width, &[if b.is_printable() { b } else { b'.' }]);
+            },
+            Kind::Named => match (bytes[0], text::name(bytes[0])) {
+                (_, Some(name)) => pad(out, self.flags, self.width, name.as_bytes()),
+                (b @ 0x80..=0xFF, None) => {
+                    pad(out, self.flags, self.width, format!("{:02x}", b).as_bytes())
+                },
+                (b, None) => pad(out, self.flags, self.width, &[b]),
+            },
+        }
+    }
+}
+
+/// A writer, which writes the bytes written to it in the given formats to the inner writer.
+///
+/// The input is processed in blocks, whose size is the largest number of bytes consumed by any of
+/// the formats, and every format shows the block from its start. Like BSD hexdump, the last unit of
+/// a format, which consumes less than the block, is repeated to fill it, unless it has an
+/// iteration count. Runs of identical blocks are replaced by a single `*` line. The input can be
+/// written in chunks of any size, and `finish` must be called when the input ends.
+pub struct Dumper<W> {
+    /// The blocks of the input.
+    blocks: Lines<W>,
+    formats: Vec<Format>,
+    /// Replace runs of identical blocks by a single `*` line.
+    squeeze: bool,
+    /// Has any input been written?
+    started: bool,
+}
+
+impl<W: Write> Dumper<W> {
+    /// Create a dumper writing to `inner`, with the first byte at address zero.
+    ///
+    /// # Panics
+    ///
+    /// This panics if none of the formats consume any input, or if one consumes more than
+    /// `MAX_BLOCK_SIZE` bytes.
+    pub fn new(inner: W, formats: Vec<Format>) -> Dumper<W> {
+        Dumper::with_address(inner, formats, 0)
+    }
+
+    /// Create a dumper writing to `inner`, labelling the first byte with the address `start`.
+    ///
+    /// # Panics
+    ///
+    /// This panics if none of the formats consume any input, or if one consumes more than
+    /// `MAX_BLOCK_SIZE` bytes.
+    pub fn with_address(inner: W, mut formats: Vec<Format>, start: u64) -> Dumper<W> {
+        let block_size = formats.iter().map(Format::bytes).max().unwrap_or(0);
+        assert!(block_size != 0, "the formats consume no input");
+        assert!(block_size <= MAX_BLOCK_SIZE, "the formats consume too many bytes");
+
+        for format in &mut formats {
+            let bytes = format.bytes();
+            if let Some(unit) = format.units.last_mut() {
+                if !unit.fixed && unit.bytes != 0 {
+                    unit.iterations += (block_size - bytes) / unit.bytes;
+                }
+            }
+        }
+
+        Dumper {
+            blocks: Lines::new(inner, block_size, start),
+            formats,
+            squeeze: true,
+            started: false,
+        }
+    }
+
+    /// Set whether runs of identical blocks are replaced by a single `*` line, which they are by
+    /// default.
+    pub fn set_squeeze(&mut self, squeeze: bool) {
+        self.squeeze = squeeze;
+    }
+
+    /// Get a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        self.blocks.get_ref()
+    }
+
+    /// Write the current block, squeezing repetitions of the previous block.
+    fn write_current(&mut self) -> io::Result<()> {
+        let formats = &self.formats;
+        self.blocks.write_line(self.squeeze, |out, address, block| {
+            for format in formats {
+                write_block(out, format, block, address);
+            }
+            Ok(())
+        })
+    }
+
+    /// Write the last block and the units showing the end address, flush the inner writer and
+    /// return it.
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.blocks.is_empty() {
+            self.write_current()?;
+        }
+
+        let address = self.blocks.address();
+        let mut inner = self.blocks.into_inner();
+        if self.started {
+            let mut out = Vec::new();
+            for unit in self.formats.iter().flat_map(|f| &f.units).filter(|u| u.is_end()) {
+                for item in &unit.items {
+                    match *item {
+                        Item::Text(ref text) => out.extend_from_slice(text),
+                        Item::Conversion(ref c) if !c.kind.consumes() => {
+                            c.write(&[], address, &mut out)
+                        },
+                        Item::Conversion(_) => (),
+                    }
+                }
+            }
+            inner.write_all(&out)?;
+        }
+        inner.flush()?;
+
+        Ok(inner)
+    }
+}
+
+/// Write a block in a format.
+///
+/// Conversions past the end of the input, which only happens for the last block, are left blank.
+fn write_block(out: &mut Vec<u8>, format: &Format, block: &[u8], address: u64) {
+    let mut pos = 0;
+    for unit in format.units.iter().filter(|u| !u.is_end()) {
+        for i in 0..unit.iterations {
+            let mut conversion_pos = pos;
+            for (n, item) in unit.items.iter().enumerate() {
+                match *item {
+                    // A trailing whitespace character of the last iteration is left out.
+                    Item::Text(ref text) if unit.iterations > 1 && i + 1 == unit.iterations
+                                            && n + 1 == unit.items.len() => {
+                        let len = match text.last() {
+                            Some(&b) if (b as char).is_whitespace() => text.len() - 1,
+                            _ => text.len(),
+                        };
+                        out.extend_from_slice(&text[..len]);
+                    },
+                    Item::Text(ref text) => out.extend_from_slice(text),
+                    Item::Conversion(ref c) if conversion_pos >= block.len() => {
+                        pad(out, c.flags, c.width, b"");
+                    },
+                    Item::Conversion(ref c) => {
+                        let end = cmp::min(conversion_pos + c.size, block.len());
+                        let mut bytes = block[conversion_pos..end].to_vec();
+                        if c.kind != Kind::String {
+                            bytes.resize(c.size, 0);
+                        }
+                        c.write(&bytes, address.wrapping_add(conversion_pos as u64), out);
+                        conversion_pos += c.size;
+                    },
+                }
+            }
+            pos += unit.bytes;
+        }
+    }
+}
+
+impl<W: Write> Write for Dumper<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.started |= !buf.is_empty();
+
+        let n = self.blocks.push(buf);
+        if self.blocks.is_full() {
+            self.write_current()?;
+        }
+
+        Ok(n)
+    }
+
+    /// Flush the inner writer. Note that the current block is not written until it is complete.
+    fn flush(&mut self) -> io::Result<()> {
+        self.blocks.get_mut().flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use lines;
+
+    fn dump(input: &[u8], formats: &[&str], chunk: usize) -> String {
+        let formats = formats.iter().map(|f| Format::parse(f).unwrap()).collect();
+        dump_formats(input, formats, chunk)
+    }
+
+    fn dump_formats(input: &[u8], formats: Vec<Format>, chunk: usize) -> String {
+        lines::test::dump(Dumper::new(Vec::new(), formats), Dumper::finish, input, chunk)
+    }
+
+    fn reason(format: &str) -> &'static str {
+        match Format::parse(format) {
+            Err(Error::InvalidFormat { reason, .. }) => reason,
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+
+    #[test]
+    fn parse() {
+        let format = Format::parse(r#"16/1 "%02x " "\n""#).unwrap();
+        assert_eq!(format.units.len(), 2);
+        assert_eq!(format.units[0].iterations, 16);
+        assert_eq!(format.units[0].bytes, 1);
+        assert_eq!(format.units[0].items, vec![
+            Item::Conversion(Conversion {
+                flags: Flags { zero: true, ..Flags::default() },
+                width: Some(2),
+                precision: None,
+                kind: Kind::Hex(false),
+                size: 1,
+            }),
+            Item::Text(b" ".to_vec()),
+        ]);
+        assert_eq!(format.units[1].items, vec![Item::Text(b"\n".to_vec())]);
+        assert_eq!(format.bytes(), 16);
+
+        let format = Format::parse(r#""%08.8_ax  " "%d%%%-5.3s""#).unwrap();
+        assert_eq!(format.units[0].bytes, 0);
+        assert_eq!(format.units[1].bytes, 7);
+
+        assert_eq!(reason(""), "empty format");
+        assert_eq!(reason("16/1"), "expected a quoted format string");
+        assert_eq!(reason(r#"4/1 "%x %x""#),
+                   "a byte count can only be given for a single conversion");
+        assert_eq!(reason(r#"/3 "%x""#), "invalid byte count for the conversion");
+        assert_eq!(reason(r#""%s""#), "a string needs a precision or a byte count");
+        assert_eq!(reason(r#""%_ay""#), "expected the address radix 'd', 'o' or 'x'");
+        assert_eq!(reason(r#""%q""#), "unknown conversion");
+        assert_eq!(reason(r#""%x"#), "unterminated format string");
+        assert_eq!(reason(r#"0/1 "%x""#), "the counts must be positive");
+    }
+
+    #[test]
+    fn limits() {
+        // The largest formats are accepted.
+        assert_eq!(Format::parse(r#"1024/1024 "%s""#).unwrap().bytes(), MAX_BLOCK_SIZE);
+        assert_eq!(Format::parse(r#""%.1048576s""#).unwrap().bytes(), MAX_BLOCK_SIZE);
+
+        let too_many = "the format consumes too many bytes";
+        assert_eq!(reason(r#"1025/1024 "%s""#), too_many);
+        assert_eq!(reason(r#"1024/1024 "%s" "%c""#), too_many);
+        assert_eq!(reason(r#"99999999999999999999/1 "%c""#), too_many);
+        assert_eq!(reason(r#"4294967296/4294967296 "%s""#), too_many);
+        assert_eq!(reason(r#"/99999999999999999999 "%s""#), too_many);
+        assert_eq!(reason(r#"1048577/1 "%_p""#), too_many);
+
+        let too_large = "the width or precision is too large";
+        assert_eq!(reason(r#""%.1048577s""#), too_large);
+        assert_eq!(reason(r#""%99999999999999999999x""#), too_large);
+        assert_eq!(reason(r#""%.99999999999999999999f""#), too_large);
+
+        // A format built by hand saturates.
+        let mut format = Format::parse(r#"2/1 "%c""#).unwrap();
+        format.units[0].iterations = usize::MAX;
+        assert_eq!(format.bytes(), usize::MAX);
+    }
+
+    #[test]
+    fn parse_file() {
+        let formats = Format::parse_file("# Comment\n\n\"%x\"\n  \n4/1 \"%c\"\n").unwrap();
+        assert_eq!(formats.len(), 2);
+
+        match Format::parse_file("\"%x\"\n\"%q\"\n") {
+            Err(Error::InvalidFormat { offset: 7, .. }) => (),
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+
+    #[test]
+    fn canonical() {
+        assert_eq!(dump_formats(b"hello world, this is hexdump\n", Format::canonical(), 5),
+                   concat!("00000000  68 65 6c 6c 6f 20 77 6f  72 6c 64 2c 20 74 68 69  ",
+                           "|hello world, thi|\n",
+                           "00000010  73 20 69 73 20 68 65 78  64 75 6d 70 0a           ",
+                           "|s is hexdump.|\n",
+                           "0000001d\n"));
+        assert_eq!(dump_formats(&[0; 64], Format::canonical(), 64),
+                   concat!("00000000  00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00  ",
+                           "|................|\n",
+                           "*\n",
+                           "00000040\n"));
+        assert_eq!(dump_formats(b"", Format::canonical(), 1), "");
+    }
+
+    #[test]
+    fn integers() {
+        let input = [0x01, 0x00, 0xFF, 0xFF, 0x80, 0x00, 0x00, 0x00];
+
+        assert_eq!(dump(&input, &[r#"4/2 "%6d" "\n""#], 8), "     1    -1   128     0\n");
+        assert_eq!(dump(&input, &[r#"2/4 "%u " "\n""#], 8), "4294901761 128\n");
+        assert_eq!(dump(&input, &[r#"/8 "%#x""#], 8), "0x80ffff0001");
+        assert_eq!(dump(&input, &[r#""%#o|%X""#], 8), "037777600001|80");
+        assert_eq!(dump(&input, &[r#"8/1 "%-+4d|""#], 3),
+                   "+1  |+0  |-1  |-1  |-128|+0  |+0  |+0  |");
+        assert_eq!(dump(&input, &[r#"8/1 "%#.3o ""#], 8), "001 000 0377 0377 0200 000 000 000");
+        assert_eq!(dump(&input[..2], &[r#""%_ad: " 2/1 "%03u ""#], 1), "0: 001 000");
+    }
+
+    #[test]
+    fn characters() {
+        let input = b"a\n\x00\xe8\x7f ";
+
+        assert_eq!(dump(input, &[r#"6/1 "%3_c""#], 6), "  a \\n \\0350177   ");
+        assert_eq!(dump(input, &[r#"6/1 "%_p""#], 6), "a.... ");
+        assert_eq!(dump(input, &[r#"6/1 "%4_u""#], 6), "   a  lf nul  e8 del    ");
+        assert_eq!(dump(&input[..3], &[r#"3/1 "%c""#], 3).as_bytes(), &input[..3]);
+        assert_eq!(dump(b"ab\0cd", &[r#"/5 "%-6s|""#], 6), "ab    |");
+        assert_eq!(dump(b"abcd", &[r#""%.3s|""#], 6), "abc|d|");
+    }
+
+    #[test]
+    fn floats() {
+        let mut input = Vec::new();
+        input.extend_from_slice(&1.5f64.to_ne_bytes());
+        input.extend_from_slice(&(-0.0001234f64).to_ne_bytes());
+        input.extend_from_slice(&1e20f32.to_ne_bytes());
+
+        assert_eq!(dump(&input, &[r#"2/8 "%f|" /4 "%f""#], 20),
+                   "1.500000|-0.000123|100000002004087734272.000000");
+        assert_eq!(dump(&input, &[r#"2/8 "%e|" /4 "%E""#], 20),
+                   "1.500000e+00|-1.234000e-04|1.000000E+20");
+        assert_eq!(dump(&input, &[r#"2/8 "%g|" /4 "%G""#], 20), "1.5|-0.0001234|1E+20");
+        assert_eq!(dump(&input, &[r#"2/8 "%+08.2f|" /4 "%10.3g""#], 20),
+                   "+0001.50|-0000.00|     1e+20");
+    }
+
+    #[test]
+    fn end_of_input() {
+        // A trailing whitespace character of the last iteration is left out, and conversions
+        // past the end of the input are left blank.
+        assert_eq!(dump(b"abc", &[r#"4/1 "%02x " "|\n""#], 3), "61 62 63   |\n");
+        assert_eq!(dump(b"abcde", &[r#"4/1 "%02x " "|\n""#], 1), "61 62 63 64|\n65         |\n");
+        // A multi-byte value is padded by zeros.
+        assert_eq!(dump(b"\x01", &[r#""%04x\n""#], 1), "0001\n");
+    }
+
+    #[test]
+    fn multiple_formats() {
+        // The block is the largest consumption, and the last unit of a shorter format repeats.
+        assert_eq!(dump(b"abcd", &[r#""%_ad " 4/1 "%02x""#, r#"" " "%_p""#, r#""\n""#], 4),
+                   "0 61626364 abcd\n");
+        assert_eq!(dump(b"abcdefgh", &[r#""%_ad " 2/1 "%c""#, r#"" " 4/1 "%02x""#, r#""\n""#], 3),
+                   "0 ab 61626364\n4 ef 65666768\n");
+    }
+}
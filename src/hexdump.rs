@@ -0,0 +1,1014 @@
+use std::cmp;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::mem;
+use std::result;
+use std::str;
+
+use bits::{decode_binary, encode_binary};
+use convert::{decode_hex, encode_hex, hex_to_u8, try_ascii_to_hex, u64_byte_array};
+use error::{Error, Result};
+use lines::Lines;
+use strings::IsPrintable;
+
+/// The layout of a dump.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Layout {
+    /// The number of bytes on each line.
+    pub cols: usize,
+    /// The number of bytes in each group. Zero means no grouping at all.
+    pub group: usize,
+    /// Show each group as a little-endian integer.
+    pub little_endian: bool,
+    /// Show every byte as eight binary digits, instead of two hexadecimal digits.
+    pub binary: bool,
+    /// The minimal number of digits in the address column. The column grows beyond this, when
+    /// needed.
+    pub address_width: usize,
+    /// The value added to every address.
+    pub displacement: u64,
+    /// Replace runs of identical lines by a single `*` line.
+    pub squeeze: bool,
+}
+
+impl Layout {
+    /// The number of bytes in each group, with the grouping disabled for a size of zero.
+    #[inline]
+    pub fn group_size(&self) -> usize {
+        if self.group == 0 || self.group > self.cols {
+            self.cols
+        } else {
+            self.group
+        }
+    }
+
+    /// The number of digits of every byte.
+    #[inline]
+    pub fn digits(&self) -> usize {
+        if self.binary {
+            8
+        } else {
+            2
+        }
+    }
+
+    /// The width of the digit part of a full line.
+    fn hex_width(&self) -> usize {
+        let group = self.group_size();
+        let groups = self.cols.div_ceil(group);
+        if self.little_endian {
+            // Every group is padded to full width.
+            groups * (group * self.digits() + 1) - 1
+        } else {
+            self.cols * self.digits() + groups - 1
+        }
+    }
+}
+
+impl Default for Layout {
+    /// The layout of 'xxd': 16 bytes on each line in groups of two bytes.
+    fn default() -> Layout {
+        Layout {
+            cols: 16,
+            group: 2,
+            little_endian: false,
+            binary: false,
+            address_width: 8,
+            displacement: 0,
+            squeeze: true,
+        }
+    }
+}
+
+/// Write an address with at least `width` hexadecimal digits, growing as needed.
+fn write_address<W: Write>(stdout: &mut W, address: u64, width: usize) -> io::Result<()> {
+    let mut digits = [0; 16];
+    encode_hex(&u64_byte_array(address), &mut digits);
+
+    let significant = 16 - address.leading_zeros() as usize / 4;
+    let width = cmp::min(cmp::max(width, significant), 16);
+    stdout.write_all(&digits[16 - width..])
+}
+
+/// Write a single line of the dump, using `digits` as scratch space for the encoded bytes.
+fn write_line<W: Write>(stdout: &mut W, digits: &mut Vec<u8>, address: u64, bytes: &[u8],
+                        layout: &Layout) -> io::Result<()> {
+    let group = layout.group_size();
+
+    // The first column, denoting the address of the first byte of the line.
+    write_address(stdout, address.wrapping_add(layout.displacement), layout.address_width)?;
+    stdout.write_all(b": ")?;
+
+    // Encode the whole line at once, and lay out the digits afterwards.
+    let size = layout.digits();
+    digits.resize(bytes.len() * size, 0);
+    if layout.binary {
+        encode_binary(bytes, digits);
+    } else {
+        encode_hex(bytes, digits);
+    }
+
+    // Now, we go over the actual data, printing it in hexadecimal or binary.
+    let mut width = 0;
+    for (n, chunk) in digits.chunks(group * size).enumerate() {
+        // Seperate the groups by a space.
+        if n != 0 {
+            stdout.write_all(b" ")?;
+            width += 1;
+        }
+
+        if layout.little_endian {
+            // The missing high bytes of a short group are left blank, like 'xxd'.
+            for _ in chunk.len() / size..group {
+                stdout.write_all(&b"        "[..size])?;
+                width += size;
+            }
+
+            for byte in chunk.rchunks(size) {
+                stdout.write_all(byte)?;
+            }
+        } else {
+            stdout.write_all(chunk)?;
+        }
+        width += chunk.len();
+    }
+
+    // Align the last column, in case the line is short.
+    for _ in width..layout.hex_width() + 2 {
+        stdout.write_all(b" ")?;
+    }
+
+    // The last column contains the printable characters. The non-printable ones are replaced with
+    // `.`.
+    for &b in bytes {
+        stdout.write_all(&[if b.is_printable() { b } else { b'.' }])?;
+    }
+    stdout.write_all(b"\n")
+}
+
+/// A writer, which writes a hexdump of the bytes written to it to the inner writer.
+///
+/// The input can be written in chunks of any size. Lines are written once they are complete, and
+/// the last, partial line is written by `finish`, which must be called when the input ends.
+pub struct HexDumper<W> {
+    lines: Lines<W>,
+    layout: Layout,
+    /// The hexadecimal digits of the current line.
+    digits: Vec<u8>,
+}
+
+impl<W: Write> HexDumper<W> {
+    /// Create a dumper writing to `inner`, with the first byte at address zero.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `layout.cols` is zero.
+    pub fn new(inner: W, layout: Layout) -> HexDumper<W> {
+        HexDumper::with_address(inner, layout, 0)
+    }
+
+    /// Create a dumper writing to `inner`, labelling the first byte with the address `start`.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `layout.cols` is zero.
+    pub fn with_address(inner: W, layout: Layout, start: u64) -> HexDumper<W> {
+        assert!(layout.cols != 0, "the column count must be positive");
+
+        HexDumper {
+            lines: Lines::new(inner, layout.cols, start),
+            layout,
+            digits: Vec::with_capacity(layout.cols * layout.digits()),
+        }
+    }
+
+    /// Get a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        self.lines.get_ref()
+    }
+
+    /// Write the current line.
+    ///
+    /// Repetitions of the previous line are squeezed, except for the last line, which is always
+    /// written, such that the length of the run is known.
+    fn write_current(&mut self, last: bool) -> io::Result<()> {
+        let layout = &self.layout;
+        let digits = &mut self.digits;
+        self.lines.write_line(layout.squeeze && !last, |out, address, line| {
+            write_line(out, digits, address, line, layout)
+        })
+    }
+
+    /// Write the last line, flush the inner writer and return it.
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.lines.is_empty() {
+            self.write_current(true)?;
+        }
+        let mut inner = self.lines.into_inner();
+        inner.flush()?;
+
+        Ok(inner)
+    }
+}
+
+impl<W: Write> Write for HexDumper<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // A complete line is only written once more input arrives, since the last line is
+        // treated specially.
+        if self.lines.is_full() {
+            self.write_current(false)?;
+        }
+
+        Ok(self.lines.push(buf))
+    }
+
+    /// Flush the inner writer. Note that the current line is not written until it is complete.
+    fn flush(&mut self) -> io::Result<()> {
+        self.lines.get_mut().flush()
+    }
+}
+
+/// Decode the hexadecimal or binary column of a line, which follows the address.
+///
+/// The column ends at the ASCII column, which is seperated by two or more spaces. In
+/// little-endian dumps, the blank space padding a short group is told apart from the seperation
+/// by its width, and by the line not being full yet. Any number of groups can be given, and a
+/// malformed group is reported by the returned reason.
+fn decode_line(mut hex: &[u8], bytes: &mut Vec<u8>, layout: &Layout)
+               -> result::Result<(), &'static str> {
+    let start = bytes.len();
+    let size = layout.group_size();
+    let byte_digits = layout.digits();
+    let mut group = Vec::with_capacity(size);
+    let is_digit = |b: u8| if layout.binary {
+        b == b'0' || b == b'1'
+    } else {
+        try_ascii_to_hex(b).is_some()
+    };
+    let (invalid, missing, partial) = if layout.binary {
+        ("invalid binary digit", "missing binary digits", "incomplete byte of binary digits")
+    } else {
+        ("invalid hexadecimal digit", "missing hexadecimal digits",
+         "odd number of hexadecimal digits")
+    };
+
+    loop {
+        let spaces = hex.iter().take_while(|&&b| b == b' ' || b == b'\t').count();
+        let digits = hex[spaces..].iter().take_while(|&&b| is_digit(b)).count();
+        let end = spaces + digits;
+
+        if end == hex.len() && digits == 0 {
+            // The end of the line.
+            break;
+        }
+
+        // The ASCII column of a full line may start with what looks like a padded group.
+        let padded = layout.little_endian && digits != 0 && digits < size * byte_digits
+                     && digits % byte_digits == 0 && end == size * byte_digits + 1
+                     && bytes.len() - start + digits / byte_digits <= layout.cols;
+        if spaces > 1 && !padded {
+            // The ASCII column is reached.
+            break;
+        }
+
+        // The group must consist of whole bytes and be followed by a space or the end of line.
+        if hex.get(end).is_some_and(|&b| b != b' ' && b != b'\t') {
+            return Err(invalid);
+        }
+        if digits == 0 {
+            return Err(missing);
+        }
+        if digits % byte_digits != 0 {
+            return Err(partial);
+        }
+
+        group.clear();
+        group.resize(digits / byte_digits, 0);
+        if layout.binary {
+            decode_binary(&hex[spaces..end], &mut group).map_err(|_| invalid)?;
+        } else {
+            decode_hex(&hex[spaces..end], &mut group).map_err(|_| invalid)?;
+        }
+        if layout.little_endian {
+            group.reverse();
+        }
+        bytes.extend_from_slice(&group);
+
+        hex = &hex[end..];
+        // A short group is always the last one.
+        if padded {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse the hexadecimal address column of a line.
+fn parse_address(address: &[u8]) -> Option<u64> {
+    str::from_utf8(address).ok().and_then(|a| u64::from_str_radix(a.trim(), 16).ok())
+}
+
+/// Reverse a dump written with the given layout, writing the bytes to `stdout`.
+///
+/// The bytes of every line are written at the position given by its address, less the
+/// displacement, and a `*` line is expanded to repetitions of the line before it. Gaps between
+/// the lines are seeked over, so they are filled with zeros, or left untouched in an existing
+/// file. Wrap a writer, which can not seek, in a `Forward`.
+pub fn reverse<R: Read, W: Write + Seek>(stdin: R, mut stdout: W, layout: &Layout) -> Result<()> {
+    // The bytes of the previous line.
+    let mut prev = Vec::with_capacity(layout.cols);
+    let mut bytes = Vec::with_capacity(layout.cols);
+    // The position in the output.
+    let mut pos = 0;
+    // Was the previous line a `*`, standing for repetitions of the line before it?
+    let mut squeezed = false;
+
+    for (n, line) in BufReader::new(stdin).split(b'\n').enumerate() {
+        let mut line = &line?[..];
+        let malformed = |reason| Error::MalformedLine {
+            line: n as u64 + 1,
+            reason,
+        };
+
+        if line.last() == Some(&b'\r') {
+            line = &line[..line.len() - 1];
+        }
+        if line.iter().all(|&b| b == b' ' || b == b'\t') {
+            continue;
+        }
+        if line == b"*" {
+            if prev.is_empty() {
+                return Err(malformed("'*' without a preceding line"));
+            }
+            squeezed = true;
+            continue;
+        }
+
+        let colon = line.iter().position(|&b| b == b':').ok_or(malformed("missing address"))?;
+        let address = parse_address(&line[..colon]).ok_or(malformed("invalid address"))?;
+        let address = address.checked_sub(layout.displacement)
+            .ok_or(malformed("address below the displacement"))?;
+
+        if squeezed {
+            // Repeat the previous line up to the address of this one.
+            while !prev.is_empty() && pos + prev.len() as u64 <= address {
+                stdout.write_all(&prev)?;
+                pos += prev.len() as u64;
+            }
+            squeezed = false;
+        }
+
+        bytes.clear();
+        decode_line(&line[colon + 1..], &mut bytes, layout).map_err(malformed)?;
+
+        if address != pos {
+            stdout.seek(SeekFrom::Start(address))?;
+        }
+        stdout.write_all(&bytes)?;
+        pos = address + bytes.len() as u64;
+
+        mem::swap(&mut prev, &mut bytes);
+    }
+
+    stdout.flush()?;
+
+    Ok(())
+}
+
+/// A writer, which can only seek forward, by writing zeros.
+///
+/// This allows sparse dumps to be reversed to the standard output.
+pub struct Forward<W> {
+    inner: W,
+    pos: u64,
+}
+
+impl<W> Forward<W> {
+    /// Create a writer at the start of `inner`.
+    pub fn new(inner: W) -> Forward<W> {
+        Forward {
+            inner,
+            pos: 0,
+        }
+    }
+}
+
+impl<W: Write> Write for Forward<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Seek for Forward<W> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match pos {
+            SeekFrom::Start(n) if n >= self.pos => {
+                self.pos += io::copy(&mut io::repeat(0).take(n - self.pos), &mut self.inner)?;
+                Ok(self.pos)
+            },
+            SeekFrom::Current(0) => Ok(self.pos),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                    "the addresses of the dump go backwards, which is only \
+                                     possible when writing to a file")),
+        }
+    }
+}
+
+/// The size of the blocks read from the input.
+const BLOCK_SIZE: usize = 64 * 1024;
+
+/// Read into the buffer until it is full or the input ends, returning the number of bytes read.
+fn read_full<R: Read>(stdin: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match stdin.read(&mut buf[n..]) {
+            Ok(0) => break,
+            Ok(m) => n += m,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(n)
+}
+
+/// Write a plain dump: continuous hexadecimal, with `cols` bytes on each line, like 'xxd -p'.
+///
+/// # Panics
+///
+/// This panics if `cols` is zero.
+pub fn write_plain<R: Read, W: Write>(stdin: R, mut stdout: W, cols: usize) -> Result<()> {
+    assert!(cols != 0, "the column count must be positive");
+
+    let mut stdin = BufReader::with_capacity(BLOCK_SIZE, stdin);
+    let mut line = vec![0; cols];
+    let mut digits = vec![0; cols * 2 + 1];
+    loop {
+        let n = read_full(&mut stdin, &mut line)?;
+        if n == 0 {
+            break;
+        }
+
+        encode_hex(&line[..n], &mut digits);
+        digits[n * 2] = b'\n';
+        stdout.write_all(&digits[..n * 2 + 1])?;
+    }
+    stdout.flush()?;
+
+    Ok(())
+}
+
+/// Reverse a plain dump, writing the bytes to `stdout`.
+///
+/// Whitespace is ignored anywhere, even between the two digits of a byte. On error, the bytes
+/// preceding the offending digit have been written.
+pub fn reverse_plain<R: Read, W: Write>(mut stdin: R, mut stdout: W) -> Result<()> {
+    let mut block = vec![0; BLOCK_SIZE];
+    let mut out = Vec::with_capacity(BLOCK_SIZE / 2);
+    // The offset of the block in the input.
+    let mut offset = 0;
+    // The first digit of a byte, which is split by the end of the block or by whitespace, and its
+    // offset.
+    let mut high = None;
+
+    loop {
+        let n = read_full(&mut stdin, &mut block)?;
+        if n == 0 {
+            break;
+        }
+
+        out.clear();
+        let res = decode_plain_block(&block[..n], offset, &mut high, &mut out);
+        // The bytes preceding an error are still written.
+        stdout.write_all(&out)?;
+        res?;
+
+        offset += n as u64;
+    }
+
+    stdout.flush()?;
+
+    match high {
+        Some((_, offset)) => Err(Error::UnpairedHexDigit { offset }),
+        None => Ok(()),
+    }
+}
+
+/// Decode a block of a plain dump, found at `offset` in the input.
+fn decode_plain_block(block: &[u8], offset: u64, high: &mut Option<(u8, u64)>, out: &mut Vec<u8>)
+                      -> Result<()> {
+    let invalid = |n: usize| Error::InvalidHexDigit {
+        offset: offset + n as u64,
+        byte: block[n],
+    };
+
+    let mut n = 0;
+    while n < block.len() {
+        if is_whitespace(block[n]) {
+            n += 1;
+            continue;
+        }
+
+        if let Some((h, _)) = high.take() {
+            let l = try_ascii_to_hex(block[n]).ok_or(invalid(n))?;
+            out.push(hex_to_u8((h, l)));
+            n += 1;
+            continue;
+        }
+
+        // Decode the run of digits up to the next whitespace at once.
+        let run = block[n..].iter().take_while(|&&b| !is_whitespace(b)).count();
+        let even = run - run % 2;
+        let start = out.len();
+        out.resize(start + even / 2, 0);
+        if let Err(err) = decode_hex(&block[n..n + even], &mut out[start..]) {
+            return Err(match err {
+                Error::InvalidHexDigit { offset: m, .. } => {
+                    // Only the bytes preceding the invalid digit are decoded.
+                    out.truncate(start + m as usize / 2);
+                    invalid(n + m as usize)
+                },
+                err => err,
+            });
+        }
+        n += even;
+
+        if even != run {
+            // The second digit of the last byte follows the whitespace.
+            let h = try_ascii_to_hex(block[n]).ok_or(invalid(n))?;
+            *high = Some((h, offset + n as u64));
+            n += 1;
+        }
+    }
+
+    Ok(())
+}
+
+/// Is this byte whitespace, which is ignored in a plain dump?
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | b'\r')
+}
+
+/// Where a dump starts in its input.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Skip {
+    /// An offset from the start of the input.
+    Start(u64),
+    /// An offset from the end of the input.
+    End(u64),
+}
+
+impl Skip {
+    /// Seek a file to the start of the dump, returning its offset.
+    ///
+    /// An offset from the end is clamped to the start of the file, rather than failing.
+    pub fn seek<S: Seek>(self, file: &mut S) -> io::Result<u64> {
+        match self {
+            Skip::Start(n) => file.seek(SeekFrom::Start(n)),
+            Skip::End(n) => {
+                let size = file.seek(SeekFrom::End(0))?;
+                file.seek(SeekFrom::Start(size.saturating_sub(n)))
+            },
+        }
+    }
+
+    /// Skip to the start of the dump in a stream, which can not be seeked, by reading and
+    /// discarding the bytes before it. The offset reached is returned, which is short of the
+    /// start when the stream ends early.
+    ///
+    /// The end of a stream is not known in advance, so an offset from the end is an error.
+    pub fn read<R: Read>(self, stdin: &mut R) -> io::Result<u64> {
+        match self {
+            Skip::Start(n) => io::copy(&mut stdin.take(n), &mut io::sink()),
+            Skip::End(_) => Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                               "can not seek relative to the end of a stream")),
+        }
+    }
+}
+
+/// The language of an array definition, written by `write_include`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Language {
+    /// `unsigned char name[] = {...}; unsigned int name_len = N;`, like 'xxd -i'.
+    C,
+    /// `pub static NAME: [u8; N] = [...];`.
+    Rust,
+}
+
+/// Derive an identifier in the given language from a file name.
+///
+/// The characters, which can not be part of an identifier, are replaced by `_`, and a name
+/// starting with a digit is prefixed by `_`. Rust identifiers are uppercased, as is the convention
+/// for statics.
+pub fn identifier(name: &str, language: Language) -> String {
+    let mut ident = String::with_capacity(name.len() + 1);
+    if name.chars().next().is_none_or(|c| c.is_ascii_digit()) {
+        ident.push('_');
+    }
+
+    for c in name.chars() {
+        let c = if c.is_ascii_alphanumeric() { c } else { '_' };
+        ident.push(match language {
+            Language::C => c,
+            Language::Rust => c.to_ascii_uppercase(),
+        });
+    }
+
+    ident
+}
+
+/// Write the bytes as an array definition in the given language, with `cols` bytes on each line.
+///
+/// Without a name, only the elements of the array are written, like 'xxd -i' does for the
+/// standard input.
+///
+/// This panics if `cols` is zero.
+pub fn write_include<W: Write>(stdout: &mut W, bytes: &[u8], name: Option<&str>,
+                               language: Language, cols: usize) -> io::Result<()> {
+    assert!(cols != 0, "the column count must be positive");
+
+    match (name, language) {
+        (Some(name), Language::C) => writeln!(stdout, "unsigned char {}[] = {{", name)?,
+        (Some(name), Language::Rust) => {
+            writeln!(stdout, "pub static {}: [u8; {}] = [", name, bytes.len())?
+        },
+        (None, _) => (),
+    }
+
+    let indent: &[u8] = match language {
+        Language::C => b"  ",
+        Language::Rust => b"    ",
+    };
+    let mut digits = vec![0; cols * 2];
+    let mut line = Vec::new();
+    for (n, chunk) in bytes.chunks(cols).enumerate() {
+        encode_hex(chunk, &mut digits);
+
+        line.clear();
+        line.extend_from_slice(indent);
+        for (i, pair) in digits[..chunk.len() * 2].chunks(2).enumerate() {
+            if i != 0 {
+                line.push(b' ');
+            }
+            line.extend_from_slice(b"0x");
+            line.extend_from_slice(pair);
+
+            // C does not allow a trailing comma after the last element, whereas Rust style wants
+            // one.
+            if n * cols + i + 1 != bytes.len() || language == Language::Rust {
+                line.push(b',');
+            }
+        }
+        line.push(b'\n');
+        stdout.write_all(&line)?;
+    }
+
+    match (name, language) {
+        (Some(name), Language::C) => {
+            writeln!(stdout, "}};\nunsigned int {}_len = {};", name, bytes.len())
+        },
+        (Some(_), Language::Rust) => stdout.write_all(b"];\n"),
+        (None, _) => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use lines;
+
+    fn dump(input: &[u8], layout: Layout, chunk: usize) -> String {
+        lines::test::dump(HexDumper::new(Vec::new(), layout), HexDumper::finish, input, chunk)
+    }
+
+    #[test]
+    fn lines() {
+        let input = b"hello world, this is hexdump\n";
+
+        assert_eq!(dump(input, Layout::default(), 64),
+                   "00000000: 6865 6c6c 6f20 776f 726c 642c 2074 6869  hello world, thi\n\
+                    00000010: 7320 6973 2068 6578 6475 6d70 0a         s is hexdump.\n");
+        assert_eq!(dump(b"", Layout::default(), 1), "");
+    }
+
+    #[test]
+    fn chunked() {
+        let input: Vec<u8> = (0..200).map(|i| (i * 7) as u8).collect();
+        let whole = dump(&input, Layout::default(), input.len());
+
+        for &chunk in &[1, 3, 15, 16, 17, 64] {
+            assert_eq!(dump(&input, Layout::default(), chunk), whole);
+        }
+    }
+
+    #[test]
+    fn layout() {
+        let layout = Layout {
+            cols: 10,
+            group: 4,
+            address_width: 4,
+            displacement: 0x100,
+            ..Layout::default()
+        };
+        assert_eq!(dump(b"abcdefghijklm", layout, 5),
+                   "0100: 61626364 65666768 696a  abcdefghij\n\
+                    010a: 6b6c6d                  klm\n");
+
+        let layout = Layout { little_endian: true, group: 4, ..Layout::default() };
+        assert_eq!(dump(b"abcdefg", layout, 2),
+                   "00000000: 64636261   676665                    abcdefg\n");
+
+        let layout = Layout { group: 0, cols: 4, ..Layout::default() };
+        assert_eq!(dump(b"abcde", layout, 2),
+                   "00000000: 61626364  abcd\n\
+                    00000004: 65        e\n");
+    }
+
+    #[test]
+    fn cols_and_groups() {
+        // The last group of a line may be short, and the ASCII column of a short line is aligned.
+        let layout = Layout { cols: 7, group: 3, ..Layout::default() };
+        assert_eq!(dump(b"abcdefghij", layout, 4),
+                   "00000000: 616263 646566 67  abcdefg\n\
+                    00000007: 68696a            hij\n");
+
+        // A group larger than the line is the whole line.
+        let layout = Layout { cols: 4, group: 8, ..Layout::default() };
+        assert_eq!(dump(b"abcde", layout, 3),
+                   "00000000: 61626364  abcd\n\
+                    00000004: 65        e\n");
+
+        let layout = Layout { cols: 1, ..Layout::default() };
+        assert_eq!(dump(b"ab", layout, 2), "00000000: 61  a\n00000001: 62  b\n");
+    }
+
+    #[test]
+    fn binary() {
+        let layout = Layout { binary: true, cols: 6, group: 1, ..Layout::default() };
+        assert_eq!(dump(b"hello\nworld", layout, 4),
+                   "00000000: 01101000 01100101 01101100 01101100 01101111 00001010  hello.\n\
+                    00000006: 01110111 01101111 01110010 01101100 01100100           world\n");
+
+        let layout = Layout { binary: true, little_endian: true, cols: 4, ..Layout::default() };
+        assert_eq!(dump(b"abc", layout, 4),
+                   "00000000: 0110001001100001         01100011  abc\n");
+    }
+
+    #[test]
+    fn squeeze() {
+        let mut input = vec![0; 64];
+        input.extend_from_slice(b"x");
+
+        assert_eq!(dump(&input, Layout { cols: 8, ..Layout::default() }, 7),
+                   "00000000: 0000 0000 0000 0000  ........\n\
+                    *\n\
+                    00000040: 78                   x\n");
+        // The last line is always written.
+        assert_eq!(dump(&input[..64], Layout { cols: 8, ..Layout::default() }, 7),
+                   "00000000: 0000 0000 0000 0000  ........\n\
+                    *\n\
+                    00000038: 0000 0000 0000 0000  ........\n");
+        assert_eq!(dump(&input[..16], Layout { cols: 8, squeeze: false, ..Layout::default() }, 16),
+                   "00000000: 0000 0000 0000 0000  ........\n\
+                    00000008: 0000 0000 0000 0000  ........\n");
+    }
+
+    #[test]
+    fn skip() {
+        let input: Vec<u8> = (0..100).collect();
+
+        let mut file = io::Cursor::new(&input);
+        assert_eq!(Skip::Start(10).seek(&mut file).unwrap(), 10);
+        assert_eq!(Skip::End(10).seek(&mut file).unwrap(), 90);
+        // Too far back is clamped to the start of the file.
+        assert_eq!(Skip::End(1000).seek(&mut file).unwrap(), 0);
+
+        let mut stream = &input[..];
+        assert_eq!(Skip::Start(10).read(&mut stream).unwrap(), 10);
+        assert_eq!(stream[0], 10);
+        assert_eq!(Skip::Start(1000).read(&mut stream).unwrap(), 90);
+        assert!(Skip::End(10).read(&mut stream).is_err());
+
+        // The address column starts at the offset, and the length limits the dump.
+        let mut file = io::Cursor::new(b"hello, world".to_vec());
+        let start = Skip::End(5).seek(&mut file).unwrap();
+        let mut dumper = HexDumper::with_address(Vec::new(), Layout::default(), start);
+        io::copy(&mut file.take(3), &mut dumper).unwrap();
+        assert_eq!(String::from_utf8(dumper.finish().unwrap()).unwrap(),
+                   "00000007: 776f 72                                  wor\n");
+    }
+
+    fn undump(dump: &str, layout: Layout) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        reverse(dump.as_bytes(), Forward::new(&mut out), &layout)?;
+        Ok(out)
+    }
+
+    fn malformed_line(res: Result<Vec<u8>>) -> (u64, &'static str) {
+        match res {
+            Err(Error::MalformedLine { line, reason }) => (line, reason),
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+
+    #[test]
+    fn reverse_lines() {
+        // With and without the ASCII column, and with a short last line.
+        let dump = "00000000: 6865 6c6c 6f20 776f 726c 642c 2074 6869  hello world, thi\n\
+                    00000010: 7320 6973 2068 6578 6475 6d70 0a         s is hexdump.\n";
+        assert_eq!(undump(dump, Layout::default()).unwrap(), b"hello world, this is hexdump\n");
+        let dump = "00000000: 6865 6c6c 6f20 776f 726c 642c 2074 6869\r\n\
+                    \n\
+                    00000010: 7320 6973 2068 6578 6475 6d70 0a\n";
+        assert_eq!(undump(dump, Layout::default()).unwrap(), b"hello world, this is hexdump\n");
+
+        // The ASCII column may look like digits.
+        assert_eq!(undump("0: 6162  ab", Layout::default()).unwrap(), b"ab");
+        assert_eq!(undump("0: 30 31  01", Layout::default()).unwrap(), b"01");
+    }
+
+    #[test]
+    fn reverse_addresses() {
+        // Gaps are filled with zeros.
+        let dump = "00000002: 6162  ab\n00000008: 63  c\n";
+        assert_eq!(undump(dump, Layout::default()).unwrap(), b"\0\0ab\0\0\0\0c");
+
+        // An existing file is patched, so the gaps are left untouched, and the addresses may go
+        // backwards.
+        let mut file = io::Cursor::new(b"0123456789".to_vec());
+        reverse(&b"00000006: 7878\n00000001: 79\n"[..], &mut file, &Layout::default()).unwrap();
+        assert_eq!(file.into_inner(), b"0y2345xx89");
+        // Which is not possible on a stream.
+        assert!(undump("00000006: 7878\n00000001: 79\n", Layout::default()).is_err());
+
+        let layout = Layout { displacement: 0x100, ..Layout::default() };
+        assert_eq!(undump("00000101: 61\n", layout).unwrap(), b"\0a");
+        assert_eq!(malformed_line(undump("00000101: 61\n000000ff: 62\n", layout)),
+                   (2, "address below the displacement"));
+    }
+
+    #[test]
+    fn reverse_squeezed() {
+        // A `*` between two lines repeats the first up to the address of the second.
+        let layout = Layout { cols: 4, ..Layout::default() };
+        assert_eq!(undump("00000000: 6162 6364  abcd\n*\n0000000c: 7878  xx\n", layout).unwrap(),
+                   b"abcdabcdabcdxx");
+
+        // Before the final address-only line, it repeats the line up to the end.
+        assert_eq!(undump("0: 0000 0000\n*\n10:\n", layout).unwrap(), [0; 16]);
+        // Only whole repetitions are written, and the rest is a gap.
+        assert_eq!(undump("0: 6161 6161\n*\n0a:\n", layout).unwrap(), b"aaaaaaaa\0\0");
+
+        // The squeezed dump of the library is reversed.
+        let mut input = vec![0; 64];
+        input.extend_from_slice(b"x");
+        let layout = Layout { cols: 8, ..Layout::default() };
+        assert!(dump(&input, layout, 7).contains("*\n"));
+        assert_eq!(undump(&dump(&input, layout, 7), layout).unwrap(), input);
+
+        // There is nothing to repeat on the first line.
+        assert_eq!(malformed_line(undump("\n*\n00000008: 00\n", layout)),
+                   (2, "'*' without a preceding line"));
+    }
+
+    #[test]
+    fn reverse_errors() {
+        let layout = Layout::default();
+        assert_eq!(malformed_line(undump("0: 61\n6162\n", layout)), (2, "missing address"));
+        assert_eq!(malformed_line(undump("0: 61\n\nzz: 62\n", layout)), (3, "invalid address"));
+        assert_eq!(malformed_line(undump("0: 61\n1: 6g\n", layout)),
+                   (2, "invalid hexadecimal digit"));
+        assert_eq!(malformed_line(undump("0: 616\n", layout)),
+                   (1, "odd number of hexadecimal digits"));
+
+        let layout = Layout { binary: true, ..Layout::default() };
+        assert_eq!(malformed_line(undump("0: 0110000\n", layout)),
+                   (1, "incomplete byte of binary digits"));
+        assert_eq!(malformed_line(undump("0: 01100002\n", layout)), (1, "invalid binary digit"));
+    }
+
+    #[test]
+    fn reverse_little_endian() {
+        // Every group is reversed, and the padding of a short group is told apart from the ASCII
+        // column by its width.
+        let layout = Layout { little_endian: true, group: 4, ..Layout::default() };
+        assert_eq!(undump("00000000: 64636261   676665                    abcdefg\n", layout)
+                       .unwrap(),
+                   b"abcdefg");
+        assert_eq!(undump("0: 64636261 6665\n", Layout { group: 2, ..layout }).unwrap(), b"abcdef");
+
+        // The ASCII column of a full line is not a padded group, even if it looks like one.
+        let dump = "00000000: 33613465 2f303638 7262696c 2f797261  e4a3860/library/\n";
+        assert_eq!(undump(dump, layout).unwrap(), b"e4a3860/library/");
+        // Unless it pads the last group of a full line.
+        let layout = Layout { cols: 6, group: 4, ..layout };
+        assert_eq!(undump("0: 64636261     6665  abcdef\n", layout).unwrap(), b"abcdef");
+    }
+
+    #[test]
+    fn reverse_round_trip() {
+        let input: Vec<u8> = (0..300).map(|i| (i * 7 + i / 5) as u8).collect();
+        let layouts = [
+            Layout::default(),
+            Layout { cols: 7, group: 3, ..Layout::default() },
+            Layout { cols: 5, group: 0, squeeze: false, ..Layout::default() },
+            Layout { little_endian: true, group: 4, ..Layout::default() },
+            Layout { little_endian: true, cols: 10, group: 8, ..Layout::default() },
+            Layout { binary: true, cols: 6, group: 1, ..Layout::default() },
+            Layout { binary: true, little_endian: true, cols: 7, group: 2, ..Layout::default() },
+            Layout { address_width: 4, displacement: 0x1000, ..Layout::default() },
+        ];
+
+        for &layout in layouts.iter() {
+            for len in [0, 1, 5, 16, 17, 300].iter() {
+                let dump = dump(&input[..*len], layout, 64);
+                assert_eq!(undump(&dump, layout).unwrap(), &input[..*len], "{}", dump);
+            }
+        }
+    }
+
+    #[test]
+    fn plain() {
+        let mut out = Vec::new();
+        write_plain(&b"hello world"[..], &mut out, 4).unwrap();
+        assert_eq!(out, b"68656c6c\n6f20776f\n726c64\n".to_vec());
+
+        let mut empty = Vec::new();
+        write_plain(&b""[..], &mut empty, 4).unwrap();
+        assert_eq!(empty, b"");
+
+        // The reverse ignores whitespace anywhere, even within a byte.
+        let mut out = Vec::new();
+        reverse_plain(&b" 6865\n6c 6\r\n\tc6f\n"[..], &mut out).unwrap();
+        assert_eq!(out, b"hello");
+
+        // The bytes preceding an error are written.
+        let mut out = Vec::new();
+        match reverse_plain(&b"6869\n6x"[..], &mut out) {
+            Err(Error::InvalidHexDigit { offset: 6, byte: b'x' }) => (),
+            res => panic!("unexpected result {:?}", res),
+        }
+        assert_eq!(out, b"hi");
+        match reverse_plain(&b"6869\n6 \n"[..], &mut Vec::new()) {
+            Err(Error::UnpairedHexDigit { offset: 5 }) => (),
+            res => panic!("unexpected result {:?}", res),
+        }
+    }
+
+    #[test]
+    fn plain_round_trip() {
+        // Long enough to split lines and digit pairs across the blocks read.
+        let input: Vec<u8> = (0..BLOCK_SIZE * 2 + 7).map(|i| (i * 7 + i / 251) as u8).collect();
+        for &cols in [1, 7, 30, 256].iter() {
+            let mut dump = Vec::new();
+            write_plain(&input[..], &mut dump, cols).unwrap();
+            let mut out = Vec::new();
+            reverse_plain(&dump[..], &mut out).unwrap();
+            assert!(out == input, "cols {}", cols);
+        }
+
+        let mut dump = vec![b' '];
+        write_plain(&input[..], &mut dump, BLOCK_SIZE).unwrap();
+        let mut out = Vec::new();
+        reverse_plain(&dump[..], &mut out).unwrap();
+        assert!(out == input);
+    }
+
+    #[test]
+    fn include() {
+        fn include(bytes: &[u8], name: Option<&str>, language: Language, cols: usize) -> String {
+            let mut out = Vec::new();
+            write_include(&mut out, bytes, name, language, cols).unwrap();
+            String::from_utf8(out).unwrap()
+        }
+
+        assert_eq!(include(b"hello\n", Some("hello_txt"), Language::C, 4),
+                   "unsigned char hello_txt[] = {\n  \
+                    0x68, 0x65, 0x6c, 0x6c,\n  \
+                    0x6f, 0x0a\n\
+                    };\n\
+                    unsigned int hello_txt_len = 6;\n");
+        assert_eq!(include(b"hello\n", Some("HELLO_TXT"), Language::Rust, 4),
+                   "pub static HELLO_TXT: [u8; 6] = [\n    \
+                    0x68, 0x65, 0x6c, 0x6c,\n    \
+                    0x6f, 0x0a,\n\
+                    ];\n");
+        assert_eq!(include(b"hi", None, Language::C, 12), "  0x68, 0x69\n");
+        assert_eq!(include(b"", Some("empty"), Language::C, 12),
+                   "unsigned char empty[] = {\n};\nunsigned int empty_len = 0;\n");
+    }
+
+    #[test]
+    fn identifiers() {
+        assert_eq!(identifier("font.psf", Language::C), "font_psf");
+        assert_eq!(identifier("font.psf", Language::Rust), "FONT_PSF");
+        assert_eq!(identifier("8x16-bold.psf", Language::C), "_8x16_bold_psf");
+        assert_eq!(identifier("", Language::Rust), "_");
+    }
+}
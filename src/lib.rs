@@ -8,7 +8,12 @@
 
 pub extern crate extra;
 
-/// Scan a byte stream for printable strings of 4 or more bytes.
+pub use error::{Error, Result};
+
+/// The error type shared by the library.
+pub mod error;
+
+/// Scan a byte stream for printable strings of a minimum length.
 pub mod strings;
 
 /// Primitives for processing bits.
@@ -16,3 +21,18 @@ pub mod bits;
 
 /// Converting between bases and endianesses
 pub mod convert;
+
+/// Formatting bytes as a stylized hexdump.
+pub mod hexdump;
+
+/// Dumping bytes in the formats of 'od'.
+pub mod od;
+
+/// The format-string language of BSD hexdump.
+pub mod format;
+
+/// The squeezed lines shared by the dumps.
+mod lines;
+
+/// The rendering of characters and floating point numbers shared by the dumps.
+mod text;
@@ -0,0 +1,139 @@
+use std::cmp;
+use std::io::{self, Write};
+
+/// The lines of a dump, with runs of identical lines squeezed into a single `*` line.
+///
+/// The bytes of the current line are collected by `push`, and `write_line` formats them and
+/// writes them to the inner writer, unless they repeat the previous line. This is shared by the
+/// dumpers, which only differ in how a line is formatted.
+pub struct Lines<W> {
+    inner: W,
+    /// The number of bytes on a full line.
+    cols: usize,
+    /// The bytes of the current line.
+    line: Vec<u8>,
+    /// The bytes of the last written line.
+    prev: Vec<u8>,
+    /// The address of the current line.
+    address: u64,
+    /// Is the current line part of a squeezed run?
+    squeezing: bool,
+    /// The formatted line, such that it is written to the inner writer at once.
+    out: Vec<u8>,
+}
+
+impl<W: Write> Lines<W> {
+    /// Create the lines of `cols` bytes, with the first byte at the address `start`.
+    pub fn new(inner: W, cols: usize, start: u64) -> Lines<W> {
+        Lines {
+            inner,
+            cols,
+            line: Vec::with_capacity(cols),
+            prev: Vec::with_capacity(cols),
+            address: start,
+            squeezing: false,
+            out: Vec::new(),
+        }
+    }
+
+    /// Append the bytes, which fit on the current line, returning their number.
+    pub fn push(&mut self, buf: &[u8]) -> usize {
+        let n = cmp::min(self.cols - self.line.len(), buf.len());
+        self.line.extend_from_slice(&buf[..n]);
+        n
+    }
+
+    /// Is the current line full?
+    pub fn is_full(&self) -> bool {
+        self.line.len() == self.cols
+    }
+
+    /// Is the current line empty?
+    pub fn is_empty(&self) -> bool {
+        self.line.is_empty()
+    }
+
+    /// The address of the current line.
+    pub fn address(&self) -> u64 {
+        self.address
+    }
+
+    /// Get a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Write the current line, and start the next one.
+    ///
+    /// If `squeeze` is set and the line repeats the previous one, a `*` line is written in its
+    /// place, once for every run. Otherwise, `format` is called with a buffer, the address and
+    /// the bytes of the line, and the buffer is written to the inner writer.
+    pub fn write_line<F>(&mut self, squeeze: bool, format: F) -> io::Result<()>
+        where F: FnOnce(&mut Vec<u8>, u64, &[u8]) -> io::Result<()> {
+        if squeeze && self.line == self.prev {
+            if !self.squeezing {
+                self.inner.write_all(b"*\n")?;
+                self.squeezing = true;
+            }
+        } else {
+            self.out.clear();
+            format(&mut self.out, self.address, &self.line)?;
+            self.inner.write_all(&self.out)?;
+
+            self.squeezing = false;
+            self.prev.clear();
+            self.prev.extend_from_slice(&self.line);
+        }
+
+        self.address = self.address.wrapping_add(self.line.len() as u64);
+        self.line.clear();
+
+        Ok(())
+    }
+
+    /// Get the inner writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    /// Write the input to a dumper in chunks of the given size, and finish the dump.
+    pub fn dump<D, F>(mut dumper: D, finish: F, input: &[u8], chunk: usize) -> String
+        where D: Write, F: FnOnce(D) -> io::Result<Vec<u8>> {
+        for part in input.chunks(chunk) {
+            dumper.write_all(part).unwrap();
+        }
+        String::from_utf8(finish(dumper).unwrap()).unwrap()
+    }
+
+    fn lines(input: &[&[u8]], squeeze: bool) -> String {
+        let mut lines = Lines::new(Vec::new(), 2, 0xfffffffffffffffe);
+        for line in input {
+            assert_eq!(lines.push(line), cmp::min(line.len(), 2));
+            lines.write_line(squeeze, |out, address, line| {
+                writeln!(out, "{:x}: {:?}", address, line)
+            }).unwrap();
+        }
+        String::from_utf8(lines.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn squeeze() {
+        let input: [&[u8]; 5] = [b"ab", b"ab", b"ab", b"cde", b"cd"];
+        assert_eq!(lines(&input, true),
+                   "fffffffffffffffe: [97, 98]\n*\n4: [99, 100]\n*\n");
+        // The address wraps around.
+        assert_eq!(lines(&input, false),
+                   "fffffffffffffffe: [97, 98]\n0: [97, 98]\n2: [97, 98]\n4: [99, 100]\n\
+                    6: [99, 100]\n");
+    }
+}
@@ -0,0 +1,437 @@
+use std::io::{self, Write};
+use std::str;
+
+use lines::Lines;
+use strings::Radix;
+use text;
+
+/// The type of the values in a dump, as given by a type specifier of 'od -t'.
+///
+/// The integer and floating point types carry their size in bytes. Values are read in the native
+/// byte order.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Type {
+    /// Named characters, like `nul` and `sp`, ignoring the high bit ('a').
+    Named,
+    /// Characters, with C escapes or octal for the non-printable ones ('c').
+    Char,
+    /// Signed decimal integers ('d').
+    Signed(usize),
+    /// Floating point numbers of 4 or 8 bytes ('f').
+    Float(usize),
+    /// Octal integers ('o').
+    Octal(usize),
+    /// Unsigned decimal integers ('u').
+    Unsigned(usize),
+    /// Hexadecimal integers ('x').
+    Hex(usize),
+}
+
+impl Type {
+    /// Parse a type string of 'od -t', which consists of one or more type specifiers.
+    ///
+    /// Integer sizes are given as 1, 2, 4 or 8 bytes, or as `C`, `S`, `I` or `L` for the sizes of
+    /// the C types, and floating point sizes as 4 or 8 bytes, or as `F` or `D`. The default sizes
+    /// are those of `int` and `double`. `None` is returned for an invalid type string.
+    pub fn parse(spec: &str) -> Option<Vec<Type>> {
+        let mut types = Vec::new();
+        let mut spec = spec.as_bytes();
+
+        while let Some((&c, rest)) = spec.split_first() {
+            spec = rest;
+
+            // The size suffix, if any.
+            let digits = spec.iter().take_while(|b| b.is_ascii_digit()).count();
+            let size = if digits != 0 {
+                let size = str::from_utf8(&spec[..digits]).ok().and_then(|s| s.parse().ok());
+                spec = &spec[digits..];
+                size
+            } else {
+                None
+            };
+            let letter = |spec: &mut &[u8], letters: &[(u8, usize)]| {
+                let size = letters.iter().find(|l| spec.first() == Some(&l.0)).map(|l| l.1);
+                if size.is_some() {
+                    *spec = &spec[1..];
+                }
+                size
+            };
+
+            types.push(match c {
+                b'a' | b'c' if size.is_some() => return None,
+                b'a' => Type::Named,
+                b'c' => Type::Char,
+                b'd' | b'o' | b'u' | b'x' => {
+                    let size = size.or_else(|| letter(&mut spec, &[(b'C', 1), (b'S', 2),
+                                                                   (b'I', 4), (b'L', 8)]));
+                    let size = size.unwrap_or(4);
+                    if size != 1 && size != 2 && size != 4 && size != 8 {
+                        return None;
+                    }
+
+                    match c {
+                        b'd' => Type::Signed(size),
+                        b'o' => Type::Octal(size),
+                        b'u' => Type::Unsigned(size),
+                        _ => Type::Hex(size),
+                    }
+                },
+                b'f' => {
+                    let size = size.or_else(|| letter(&mut spec, &[(b'F', 4), (b'D', 8)]));
+                    match size.unwrap_or(8) {
+                        4 => Type::Float(4),
+                        8 => Type::Float(8),
+                        _ => return None,
+                    }
+                },
+                _ => return None,
+            });
+        }
+
+        if types.is_empty() {
+            None
+        } else {
+            Some(types)
+        }
+    }
+
+    /// The number of bytes in a value.
+    #[inline]
+    pub fn size(self) -> usize {
+        match self {
+            Type::Named | Type::Char => 1,
+            Type::Signed(n) | Type::Float(n) | Type::Octal(n) | Type::Unsigned(n) => n,
+            Type::Hex(n) => n,
+        }
+    }
+
+    /// The width of the widest value, not counting the separating space.
+    fn width(self) -> usize {
+        match self {
+            Type::Named | Type::Char => 3,
+            Type::Signed(1) => 4,
+            Type::Signed(2) => 6,
+            Type::Signed(4) => 11,
+            Type::Signed(_) => 20,
+            Type::Float(4) => 15,
+            Type::Float(_) => 24,
+            Type::Octal(n) => (n * 8).div_ceil(3),
+            Type::Unsigned(1) => 3,
+            Type::Unsigned(2) => 5,
+            Type::Unsigned(4) => 10,
+            Type::Unsigned(_) => 20,
+            Type::Hex(n) => n * 2,
+        }
+    }
+
+    /// Write the value of the bytes, right-aligned to `width`.
+    fn write_value(self, bytes: &[u8], width: usize, out: &mut Vec<u8>) -> io::Result<()> {
+        let value = native(bytes);
+        let bits = bytes.len() as u32 * 8;
+
+        match self {
+            Type::Named => {
+                // Like POSIX, unlike BSD hexdump, a newline is `nl` and a space `sp`.
+                let b = bytes[0] & 0x7F;
+                let name = match b {
+                    b'\n' => Some("nl"),
+                    b' ' => Some("sp"),
+                    b => text::name(b),
+                };
+                text::pad(out, false, width, name.map_or(&[b][..], str::as_bytes));
+                Ok(())
+            },
+            Type::Char => {
+                let mut escape = Vec::with_capacity(3);
+                text::escape(bytes[0], &mut escape);
+                text::pad(out, false, width, &escape);
+                Ok(())
+            },
+            Type::Signed(_) => {
+                // Sign extend the value.
+                let shift = 64 - bits;
+                write!(out, "{:>1$}", ((value << shift) as i64) >> shift, width)
+            },
+            Type::Float(4) => {
+                write!(out, "{:>1$}", text::shortest(f32::from_bits(value as u32), 6), width)
+            },
+            Type::Float(_) => {
+                write!(out, "{:>1$}", text::shortest(f64::from_bits(value), 15), width)
+            },
+            Type::Octal(_) => write!(out, "{:>1$}", format!("{:01$o}", value, self.width()), width),
+            Type::Unsigned(_) => write!(out, "{:>1$}", value, width),
+            Type::Hex(_) => write!(out, "{:>1$}", format!("{:01$x}", value, self.width()), width),
+        }
+    }
+}
+
+/// Read an integer of up to 8 bytes in the native byte order.
+fn native(bytes: &[u8]) -> u64 {
+    let mut buf = [0; 8];
+    if cfg!(target_endian = "little") {
+        buf[..bytes.len()].copy_from_slice(bytes);
+    } else {
+        buf[8 - bytes.len()..].copy_from_slice(bytes);
+    }
+    u64::from_ne_bytes(buf)
+}
+
+/// The options of a dump.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Options {
+    /// The types the bytes are shown as. Each type gets a line of its own.
+    pub types: Vec<Type>,
+    /// The radix of the address column, or `None` to leave it out.
+    pub address: Option<Radix>,
+    /// The number of bytes on each line, which must be a multiple of the sizes of the types.
+    pub cols: usize,
+    /// Replace runs of identical lines by a single `*` line.
+    pub squeeze: bool,
+}
+
+impl Default for Options {
+    /// The defaults of 'od': 16 bytes on each line, shown as 2 byte octal words.
+    fn default() -> Options {
+        Options {
+            types: vec![Type::Octal(2)],
+            address: Some(Radix::Octal),
+            cols: 16,
+            squeeze: true,
+        }
+    }
+}
+
+/// Write an address in the given radix, like 'od'.
+fn write_address<W: Write>(stdout: &mut W, address: u64, radix: Radix) -> io::Result<()> {
+    match radix {
+        Radix::Decimal => write!(stdout, "{:07}", address),
+        Radix::Octal => write!(stdout, "{:07o}", address),
+        Radix::Hexadecimal => write!(stdout, "{:06x}", address),
+    }
+}
+
+/// A writer, which writes an 'od' style dump of the bytes written to it to the inner writer.
+///
+/// Every line holds the bytes in each of the types, with a line of its own for every type, and
+/// the values are aligned across the types. The input can be written in chunks of any size, and
+/// `finish` must be called when the input ends, to write the last line and the final address.
+pub struct Dumper<W> {
+    lines: Lines<W>,
+    options: Options,
+    /// The extra width of each field of each type, which aligns the values across the types.
+    padding: Vec<Vec<usize>>,
+}
+
+impl<W: Write> Dumper<W> {
+    /// Create a dumper writing to `inner`, with the first byte at address zero.
+    ///
+    /// # Panics
+    ///
+    /// This panics if there are no types, or the column count is zero or not a multiple of the
+    /// sizes of the types.
+    pub fn new(inner: W, options: Options) -> Dumper<W> {
+        Dumper::with_address(inner, options, 0)
+    }
+
+    /// Create a dumper writing to `inner`, labelling the first byte with the address `start`.
+    ///
+    /// # Panics
+    ///
+    /// See `new`.
+    pub fn with_address(inner: W, options: Options, start: u64) -> Dumper<W> {
+        assert!(!options.types.is_empty(), "no types are given");
+        assert!(options.cols != 0 && options.types.iter().all(|t| options.cols.is_multiple_of(t.size())),
+                "the column count must be a positive multiple of the sizes of the types");
+
+        // Like 'od', the line of each type is padded to the width of the widest one, and the
+        // padding is spread evenly over its fields.
+        let line_width = |t: &Type| (t.width() + 1) * (options.cols / t.size());
+        let widest = options.types.iter().map(&line_width).max().unwrap_or(0);
+        let padding = options.types.iter().map(|t| {
+            let fields = options.cols / t.size();
+            let pad = widest - line_width(t);
+            (0..fields).map(|i| pad * (i + 1) / fields - pad * i / fields).collect()
+        }).collect();
+
+        Dumper {
+            lines: Lines::new(inner, options.cols, start),
+            options,
+            padding,
+        }
+    }
+
+    /// Get a reference to the inner writer.
+    pub fn get_ref(&self) -> &W {
+        self.lines.get_ref()
+    }
+
+    /// Write the current line, squeezing repetitions of the previous line.
+    fn write_current(&mut self) -> io::Result<()> {
+        let options = &self.options;
+        let padding = &self.padding;
+        self.lines.write_line(options.squeeze, |out, address, line| {
+            for (n, (&t, padding)) in options.types.iter().zip(padding).enumerate() {
+                // The lines of the other types are indented by the width of the address.
+                match options.address {
+                    Some(radix) if n == 0 => write_address(out, address, radix)?,
+                    Some(Radix::Hexadecimal) => out.extend_from_slice(b"      "),
+                    Some(_) => out.extend_from_slice(b"       "),
+                    None => (),
+                }
+
+                // A short value at the end of the input is padded by zeros.
+                for (chunk, &pad) in line.chunks(t.size()).zip(padding) {
+                    let mut value = [0; 8];
+                    value[..chunk.len()].copy_from_slice(chunk);
+                    out.push(b' ');
+                    t.write_value(&value[..t.size()], t.width() + pad, out)?;
+                }
+                out.push(b'\n');
+            }
+            Ok(())
+        })
+    }
+
+    /// Write the last line and the final address, flush the inner writer and return it.
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.lines.is_empty() {
+            self.write_current()?;
+        }
+        let address = self.lines.address();
+        let mut inner = self.lines.into_inner();
+        if let Some(radix) = self.options.address {
+            write_address(&mut inner, address, radix)?;
+            inner.write_all(b"\n")?;
+        }
+        inner.flush()?;
+
+        Ok(inner)
+    }
+}
+
+impl<W: Write> Write for Dumper<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.lines.push(buf);
+        if self.lines.is_full() {
+            self.write_current()?;
+        }
+
+        Ok(n)
+    }
+
+    /// Flush the inner writer. Note that the current line is not written until it is complete.
+    fn flush(&mut self) -> io::Result<()> {
+        self.lines.get_mut().flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use lines;
+
+    fn dump(input: &[u8], options: Options, chunk: usize) -> String {
+        lines::test::dump(Dumper::new(Vec::new(), options), Dumper::finish, input, chunk)
+    }
+
+    fn types(spec: &str) -> Options {
+        Options {
+            types: Type::parse(spec).unwrap(),
+            ..Options::default()
+        }
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(Type::parse("x1"), Some(vec![Type::Hex(1)]));
+        assert_eq!(Type::parse("d"), Some(vec![Type::Signed(4)]));
+        assert_eq!(Type::parse("uLoSf"), Some(vec![Type::Unsigned(8), Type::Octal(2),
+                                                   Type::Float(8)]));
+        assert_eq!(Type::parse("fFca"), Some(vec![Type::Float(4), Type::Char, Type::Named]));
+        assert_eq!(Type::parse("x2c"), Some(vec![Type::Hex(2), Type::Char]));
+        assert_eq!(Type::parse(""), None);
+        assert_eq!(Type::parse("x3"), None);
+        assert_eq!(Type::parse("f2"), None);
+        assert_eq!(Type::parse("c1"), None);
+        assert_eq!(Type::parse("q"), None);
+    }
+
+    #[test]
+    fn integers() {
+        let input = b"\x01\x00\xff\xff\x80\x00\x00\x00\x10\x00\x00\x00\x00\x00\x00\x80";
+
+        assert_eq!(dump(input, types("o2"), 16),
+                   "0000000 000001 177777 000200 000000 000020 000000 000000 100000\n\
+                    0000020\n");
+        assert_eq!(dump(input, types("x1"), 16),
+                   "0000000 01 00 ff ff 80 00 00 00 10 00 00 00 00 00 00 80\n\
+                    0000020\n");
+        assert_eq!(dump(input, types("d2"), 16),
+                   "0000000      1     -1    128      0     16      0      0 -32768\n\
+                    0000020\n");
+        assert_eq!(dump(input, types("u4"), 16),
+                   "0000000 4294901761        128         16 2147483648\n\
+                    0000020\n");
+        assert_eq!(dump(input, types("d8"), 16),
+                   "0000000         554050715649 -9223372036854775792\n\
+                    0000020\n");
+    }
+
+    #[test]
+    fn floats() {
+        let mut input = Vec::new();
+        input.extend_from_slice(&1.5f32.to_ne_bytes());
+        input.extend_from_slice(&(-0.1f32).to_ne_bytes());
+        input.extend_from_slice(&1e20f64.to_ne_bytes());
+        input.extend_from_slice(&1e-5f64.to_ne_bytes());
+        input.extend_from_slice(&123456789.125f64.to_ne_bytes());
+        input.extend_from_slice(&100f32.to_ne_bytes());
+        input.extend_from_slice(&71278720f32.to_ne_bytes());
+        input.extend_from_slice(&f32::NAN.to_ne_bytes());
+        input.extend_from_slice(&f32::NEG_INFINITY.to_ne_bytes());
+
+        assert_eq!(dump(&input[..16], Options { address: None, ..types("fD") }, 16),
+                   "   -5.238690728502464e-11                    1e+20\n");
+        assert_eq!(dump(&input[..16], Options { address: None, ..types("fF") }, 16),
+                   "             1.5            -0.1   2.9457823e+34       598.73615\n");
+        assert_eq!(dump(&input[16..32], Options { address: None, ..types("f8") }, 16),
+                   "                    1e-05            123456789.125\n");
+        assert_eq!(dump(&input[32..], Options { address: None, ..types("f4") }, 16),
+                   "             100    7.127872e+07             nan            -inf\n");
+    }
+
+    #[test]
+    fn chars() {
+        let input = b"hi\tthere\n\x00\x7f\xe8 ";
+
+        assert_eq!(dump(input, types("c"), 16),
+                   "0000000   h   i  \\t   t   h   e   r   e  \\n  \\0 177 350    \n\
+                    0000015\n");
+        assert_eq!(dump(input, types("a"), 16),
+                   "0000000   h   i  ht   t   h   e   r   e  nl nul del   h  sp\n\
+                    0000015\n");
+    }
+
+    #[test]
+    fn stacked() {
+        assert_eq!(dump(b"abc\n", Options { address: Some(Radix::Hexadecimal), ..types("x2c") }, 1),
+                   "000000    6261    0a63\n\
+                    \x20        a   b   c  \\n\n\
+                    000004\n");
+    }
+
+    #[test]
+    fn squeeze() {
+        let input = [0; 64];
+
+        assert_eq!(dump(&input, Options { cols: 8, ..types("x8") }, 5),
+                   "0000000 0000000000000000\n\
+                    *\n\
+                    0000100\n");
+        assert_eq!(dump(&input[..16], Options { cols: 8, squeeze: false, ..types("x8") }, 64),
+                   "0000000 0000000000000000\n\
+                    0000010 0000000000000000\n\
+                    0000020\n");
+        assert_eq!(dump(b"", Options::default(), 1), "0000000\n");
+    }
+}
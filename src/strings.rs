@@ -1,89 +1,484 @@
-use std::io::{Write, Read, Stderr};
+use std::cmp;
+use std::io::{self, Write, Read};
+use std::mem;
+use std::str;
 
-use extra::option::OptionalExt;
+use convert::{u8_to_hex, hex_to_ascii};
+use error::Result;
 
 /// A trait for characters/bytes that can be printable.
 pub trait IsPrintable {
     /// Is this character printable?
+    #[allow(clippy::wrong_self_convention)]
     fn is_printable(self) -> bool;
 }
 
 impl IsPrintable for u8 {
     #[inline]
     fn is_printable(self) -> bool {
-        // TODO handle unicode.
-        self >= 0x20 && self <= 0x7e
+        // Only ASCII. Multibyte UTF-8 sequences are handled through `char`.
+        (0x20..=0x7e).contains(&self)
     }
 }
 
-/// A buffer tracking the previous printable characters.
-#[derive(Copy, Clone)]
-struct Trailing {
-    chars: [u8; 4],
-    current: usize,
+impl IsPrintable for char {
+    #[inline]
+    fn is_printable(self) -> bool {
+        !self.is_control()
+    }
 }
 
-// Wow, such premature, much optimization
-#[allow(dead_code)] // DAFUQ rustc?
-impl Trailing {
+/// The minimum string length used when none is given, matching GNU strings.
+pub const DEFAULT_MIN_LENGTH: usize = 4;
+
+/// The radix used to print the offset of each string.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Radix {
+    /// Base 10.
+    Decimal,
+    /// Base 8.
+    Octal,
+    /// Base 16.
+    Hexadecimal,
+}
+
+impl Radix {
+    /// Write an offset in this radix, right-aligned and followed by a space, like GNU strings.
+    fn write_offset<W: Write>(self, offset: u64, stdout: &mut W) -> io::Result<()> {
+        match self {
+            Radix::Decimal => write!(stdout, "{:7} ", offset),
+            Radix::Octal => write!(stdout, "{:7o} ", offset),
+            Radix::Hexadecimal => write!(stdout, "{:7x} ", offset),
+        }
+    }
+}
+
+/// The character encoding strings are searched for in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    /// 7-bit ASCII, one byte per character. This is the default.
+    Ascii,
+    /// 8-bit bytes, where every byte above 0x7F is treated as printable as well. These bytes are
+    /// written as-is.
+    EightBit,
+    /// 16-bit big-endian code units.
+    Utf16Be,
+    /// 16-bit little-endian code units.
+    Utf16Le,
+    /// 32-bit big-endian code units.
+    Ucs4Be,
+    /// 32-bit little-endian code units.
+    Ucs4Le,
+}
+
+impl Encoding {
+    /// The size of a single code unit in bytes.
     #[inline]
-    fn new() -> Trailing {
-        Trailing {
-            chars: [0; 4],
-            current: 0,
+    pub fn unit_size(self) -> usize {
+        match self {
+            Encoding::Ascii | Encoding::EightBit => 1,
+            Encoding::Utf16Be | Encoding::Utf16Le => 2,
+            Encoding::Ucs4Be | Encoding::Ucs4Le => 4,
         }
     }
 
+    /// Decode a code unit of `unit_size` bytes to its numeric value.
     #[inline]
-    fn set(&mut self, b: u8) -> bool {
-        self.chars[self.current] = b;
-        self.current += 1;
+    fn code_point(self, unit: &[u8]) -> u32 {
+        match self {
+            Encoding::Ascii | Encoding::EightBit | Encoding::Utf16Be | Encoding::Ucs4Be => {
+                unit.iter().fold(0, |acc, &b| acc << 8 | b as u32)
+            },
+            Encoding::Utf16Le | Encoding::Ucs4Le => {
+                unit.iter().rev().fold(0, |acc, &b| acc << 8 | b as u32)
+            },
+        }
+    }
 
-        self.is_complete()
+    /// Is this code point a printable character in this encoding?
+    ///
+    /// Like GNU strings, the wide encodings only consider characters up to 0xFF. Besides ASCII,
+    /// this includes the printable part of the Latin-1 supplement.
+    #[inline]
+    fn is_printable(self, c: u32) -> bool {
+        match self {
+            Encoding::Ascii => c < 0x80 && (c as u8).is_printable(),
+            Encoding::EightBit => c >= 0x80 || (c as u8).is_printable(),
+            _ => (c < 0x80 && (c as u8).is_printable()) || (0xA0..=0xFF).contains(&c),
+        }
     }
 
+    /// Encode a printable code point for output into `buf`.
+    ///
+    /// Single byte encodings are written verbatim, and wide characters are written as UTF-8.
     #[inline]
-    fn reset(&mut self) {
-        self.current = 0;
+    fn encode(self, c: u32, buf: &mut Vec<u8>) {
+        match self {
+            Encoding::Ascii | Encoding::EightBit => buf.push(c as u8),
+            _ => {
+                let c = char::from_u32(c).expect("printable code points are valid characters");
+                buf.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+            },
+        }
+    }
+}
+
+/// How multibyte UTF-8 sequences are treated by the single byte encodings.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Unicode {
+    /// No special treatment. The bytes are treated like any other byte of the encoding. This is
+    /// the default.
+    Default,
+    /// Printable characters are part of strings, and are written as UTF-8.
+    Locale,
+    /// Printable characters are part of strings, and are written as `\uXXXX` escapes.
+    Escape,
+    /// Multibyte sequences are never printable, even in the 8-bit encoding.
+    Invalid,
+    /// Printable characters are part of strings, and are written as their hexadecimal bytes
+    /// enclosed in `<>`.
+    Hex,
+}
+
+impl Unicode {
+    /// Render the character `c`, encoded by the UTF-8 sequence `seq`, into `buf`.
+    ///
+    /// Returns false if the character is not printable.
+    fn render(self, c: char, seq: &[u8], buf: &mut Vec<u8>) -> bool {
+        if self == Unicode::Invalid || !c.is_printable() {
+            return false;
+        }
+
+        match self {
+            Unicode::Escape if (c as u32) < 0x10000 => {
+                write!(buf, "\\u{:04x}", c as u32).unwrap();
+            },
+            Unicode::Escape => write!(buf, "\\U{:08x}", c as u32).unwrap(),
+            Unicode::Hex => {
+                buf.extend_from_slice(b"<0x");
+                for &b in seq {
+                    let (h, l) = u8_to_hex(b);
+                    buf.extend_from_slice(&[hex_to_ascii(h), hex_to_ascii(l)]);
+                }
+                buf.push(b'>');
+            },
+            _ => buf.extend_from_slice(seq),
+        }
+
+        true
+    }
+}
+
+/// The length of the UTF-8 sequence started by `lead`, if it is a multibyte lead byte.
+#[inline]
+fn utf8_len(lead: u8) -> Option<usize> {
+    match lead {
+        0xC2..=0xDF => Some(2),
+        0xE0..=0xEF => Some(3),
+        0xF0..=0xF4 => Some(4),
+        _ => None,
+    }
+}
+
+/// The size of the blocks read from the input.
+const BLOCK_SIZE: usize = 64 * 1024;
+
+/// A byte stream read in blocks, which allows pushing back bytes read ahead.
+struct Input<R> {
+    stdin: R,
+    /// The current block.
+    block: Vec<u8>,
+    /// The position of the next byte in the block.
+    pos: usize,
+    /// The number of bytes in the block.
+    len: usize,
+    /// The pushed back bytes, in reverse order.
+    pending: Vec<u8>,
+}
+
+impl<R: Read> Input<R> {
+    fn new(stdin: R) -> Input<R> {
+        Input {
+            stdin,
+            block: vec![0; BLOCK_SIZE],
+            pos: 0,
+            len: 0,
+            pending: Vec::new(),
+        }
     }
 
     #[inline]
-    fn is_complete(self) -> bool {
-        self.current == 4
+    fn next(&mut self) -> Option<io::Result<u8>> {
+        if let Some(b) = self.pending.pop() {
+            return Some(Ok(b));
+        }
+
+        while self.pos == self.len {
+            match self.stdin.read(&mut self.block) {
+                Ok(0) => return None,
+                Ok(n) => {
+                    self.pos = 0;
+                    self.len = n;
+                },
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        let b = self.block[self.pos];
+        self.pos += 1;
+        Some(Ok(b))
     }
 
     #[inline]
-    fn chars(self) -> [u8; 4] {
-        self.chars
+    fn push_back(&mut self, bytes: &[u8]) {
+        self.pending.extend(bytes.iter().rev());
     }
 }
 
-/// Read a stream of bytes and output printable strings of length 4 or more seperated by 0x0A
-/// (NL)
-pub fn read<R: Read, W: Write>(stdin: R, mut stdout: W, mut stderr: Stderr) {
-    let mut trailing = Trailing::new();
+/// Options controlling which strings are found and how they are printed.
+#[derive(Copy, Clone, Debug)]
+pub struct Options {
+    /// The minimum length of a string. Zero is treated as one.
+    pub min: usize,
+    /// Prefix every string by its offset in the stream, printed in this radix.
+    pub radix: Option<Radix>,
+    /// The character encoding to search for.
+    pub encoding: Encoding,
+    /// The treatment of UTF-8 sequences. This only affects the single byte encodings.
+    pub unicode: Unicode,
+}
 
-    for i in stdin.bytes() {
-        let i = i.try(&mut stderr);
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            min: DEFAULT_MIN_LENGTH,
+            radix: None,
+            encoding: Encoding::Ascii,
+            unicode: Unicode::Default,
+        }
+    }
+}
 
-        if i.is_printable() {
-            if trailing.is_complete() {
-                stdout.write(&[i]).try(&mut stderr);
-            } else if trailing.set(i) {
-                stdout.write(&trailing.chars()).try(&mut stderr);
-            }
+/// A string of printable characters found by `StringScanner`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScannedString {
+    /// The offset of the string's first character in the stream.
+    pub offset: u64,
+    /// The encoding the string was found in.
+    pub encoding: Encoding,
+    /// The number of characters in the string.
+    pub len: usize,
+    /// The characters, as they are written by `read`.
+    ///
+    /// This is UTF-8, unless the string was found in `Encoding::EightBit` and contains bytes above
+    /// 0x7F, which are kept verbatim.
+    pub bytes: Vec<u8>,
+}
+
+impl ScannedString {
+    /// Get the characters as a string slice, if they are valid UTF-8.
+    pub fn as_str(&self) -> Option<&str> {
+        str::from_utf8(&self.bytes).ok()
+    }
+}
+
+/// A buffer tracking the previous printable characters.
+struct Trailing {
+    /// The encoded characters.
+    chars: Vec<u8>,
+    /// The number of characters in the buffer.
+    len: usize,
+    min: usize,
+    /// The offset of the first character in the buffer.
+    start: u64,
+}
+
+impl Trailing {
+    #[inline]
+    fn new(min: usize) -> Trailing {
+        Trailing {
+            chars: Vec::new(),
+            len: 0,
+            min,
+            start: 0,
+        }
+    }
+
+    #[inline]
+    fn set(&mut self, c: &[u8], offset: u64) {
+        if self.len == 0 {
+            self.start = offset;
+        }
+        self.chars.extend_from_slice(c);
+        self.len += 1;
+    }
+
+    /// Take the buffered characters, if there are enough of them to form a string.
+    ///
+    /// The buffer is reset either way.
+    #[inline]
+    fn take(&mut self, encoding: Encoding) -> Option<ScannedString> {
+        let len = self.len;
+        self.len = 0;
+
+        if len >= self.min {
+            Some(ScannedString {
+                offset: self.start,
+                encoding,
+                len,
+                bytes: mem::take(&mut self.chars),
+            })
         } else {
-            if trailing.is_complete() {
-                stdout.write(b"\n").try(&mut stderr);
+            self.chars.clear();
+            None
+        }
+    }
+}
+
+/// An iterator over the printable strings of a byte stream.
+///
+/// Any `Read` can be scanned, including in-memory `&[u8]` slices. The stream is read in code
+/// units of `options.encoding`. A trailing partial code unit is ignored. Unless `options.unicode`
+/// is `Unicode::Default`, valid multibyte UTF-8 sequences are decoded, and count as a single
+/// character. `options.radix` is ignored.
+pub struct StringScanner<R> {
+    stdin: Input<R>,
+    options: Options,
+    trailing: Trailing,
+    /// The offset of the next code unit.
+    offset: u64,
+    /// The rendering of the current character.
+    buf: Vec<u8>,
+}
+
+impl<R: Read> StringScanner<R> {
+    /// Create a scanner reading from `stdin`.
+    pub fn new(stdin: R, options: Options) -> StringScanner<R> {
+        StringScanner {
+            stdin: Input::new(stdin),
+            options,
+            trailing: Trailing::new(cmp::max(options.min, 1)),
+            offset: 0,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Read the next character, rendering it into `self.buf` if it is printable.
+    ///
+    /// Returns `None` at the end of the stream, and whether the character is printable
+    /// otherwise.
+    fn next_char(&mut self) -> io::Result<Option<bool>> {
+        let encoding = self.options.encoding;
+        let size = encoding.unit_size();
+
+        // Collect the bytes of the next code unit.
+        let mut unit = [0; 4];
+        for b in unit[..size].iter_mut() {
+            *b = match self.stdin.next() {
+                Some(x) => x?,
+                None => return Ok(None),
+            };
+        }
+        let mut len = size;
+
+        self.buf.clear();
+        let lead = if size == 1 && self.options.unicode != Unicode::Default {
+            utf8_len(unit[0])
+        } else {
+            None
+        };
+        let printable = match lead {
+            Some(n) => {
+                // Read the rest of the sequence.
+                let mut seq = [unit[0], 0, 0, 0];
+                while len < n {
+                    seq[len] = match self.stdin.next() {
+                        Some(x) => x?,
+                        None => break,
+                    };
+                    len += 1;
+                }
+
+                match str::from_utf8(&seq[..len]).ok().and_then(|s| s.chars().next()) {
+                    Some(c) => self.options.unicode.render(c, &seq[..len], &mut self.buf),
+                    None => {
+                        // Not valid UTF-8, so the lead byte is treated on its own.
+                        self.stdin.push_back(&seq[1..len]);
+                        len = 1;
+                        let printable = encoding.is_printable(unit[0] as u32);
+                        if printable {
+                            encoding.encode(unit[0] as u32, &mut self.buf);
+                        }
+                        printable
+                    },
+                }
+            },
+            None => {
+                let c = encoding.code_point(&unit[..size]);
+                let printable = encoding.is_printable(c);
+                if printable {
+                    encoding.encode(c, &mut self.buf);
+                } else {
+                    // A string may start at any offset, so only the first byte is skipped.
+                    self.stdin.push_back(&unit[1..size]);
+                    len = 1;
+                }
+                printable
+            },
+        };
+
+        self.offset += len as u64;
+        Ok(Some(printable))
+    }
+}
+
+impl<R: Read> Iterator for StringScanner<R> {
+    type Item = io::Result<ScannedString>;
+
+    fn next(&mut self) -> Option<io::Result<ScannedString>> {
+        let encoding = self.options.encoding;
+
+        loop {
+            let offset = self.offset;
+            match self.next_char() {
+                Ok(Some(true)) => self.trailing.set(&self.buf, offset),
+                Ok(Some(false)) => {
+                    if let Some(found) = self.trailing.take(encoding) {
+                        return Some(Ok(found));
+                    }
+                },
+                // The end of the stream terminates the last string.
+                Ok(None) => return self.trailing.take(encoding).map(Ok),
+                Err(err) => return Some(Err(err)),
             }
-            trailing.reset();
         }
     }
 }
 
+/// Read a stream of bytes and output printable strings of length `options.min` or more
+/// seperated by 0x0A (NL)
+///
+/// See `StringScanner` for the details of the scanning. If `options.radix` is set, every string
+/// is prefixed by its offset.
+pub fn read<R: Read, W: Write>(stdin: R, mut stdout: W, options: Options) -> Result<()> {
+    for found in StringScanner::new(stdin, options) {
+        let found = found?;
+
+        if let Some(radix) = options.radix {
+            radix.write_offset(found.offset, &mut stdout)?;
+        }
+        stdout.write_all(&found.bytes)?;
+        stdout.write_all(b"\n")?;
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use error::Error;
 
     #[test]
     fn printable() {
@@ -97,4 +492,176 @@ mod test {
         assert!(b'x'.is_printable());
         assert!(b'~'.is_printable());
     }
+
+    fn strings(input: &[u8], min: usize) -> Vec<u8> {
+        strings_with(input, Options { min, ..Options::default() })
+    }
+
+    fn strings_with(input: &[u8], options: Options) -> Vec<u8> {
+        let mut out = Vec::new();
+        read(input, &mut out, options).unwrap();
+        out
+    }
+
+    #[test]
+    fn min_length() {
+        let input = b"ab\0abcd\0abcdefgh\x01abcdefghijklmnop\0";
+
+        assert_eq!(strings(input, 4), b"abcd\nabcdefgh\nabcdefghijklmnop\n".to_vec());
+        assert_eq!(strings(input, 8), b"abcdefgh\nabcdefghijklmnop\n".to_vec());
+        assert_eq!(strings(input, 16), b"abcdefghijklmnop\n".to_vec());
+        assert_eq!(strings(input, 17), b"".to_vec());
+        assert_eq!(strings(input, 0), strings(input, 1));
+    }
+
+    #[test]
+    fn read_error() {
+        /// A reader, which fails after its input.
+        struct Failing<'a>(&'a [u8]);
+
+        impl<'a> io::Read for Failing<'a> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if self.0.is_empty() {
+                    return Err(io::Error::other("broken pipe"));
+                }
+                self.0.read(buf)
+            }
+        }
+
+        // The error is returned, rather than exiting the process.
+        let mut out = Vec::new();
+        match read(Failing(b"abcd\0efgh\0ij"), &mut out, Options::default()) {
+            Err(Error::Io(ref e)) if e.kind() == io::ErrorKind::Other => (),
+            res => panic!("unexpected result {:?}", res),
+        }
+        assert_eq!(out, b"abcd\nefgh\n".to_vec());
+    }
+
+    #[test]
+    fn huge_min_length() {
+        assert_eq!(strings(b"abcd\0abcdefgh\0", usize::MAX), b"".to_vec());
+    }
+
+    #[test]
+    fn offsets() {
+        let mut input = vec![0; 100];
+        input.extend_from_slice(b"first\0\0second\0");
+
+        let radix = |radix| Options { radix: Some(radix), ..Options::default() };
+
+        assert_eq!(strings_with(&input, radix(Radix::Decimal)),
+                   b"    100 first\n    107 second\n".to_vec());
+        assert_eq!(strings_with(&input, radix(Radix::Octal)),
+                   b"    144 first\n    153 second\n".to_vec());
+        assert_eq!(strings_with(&input, radix(Radix::Hexadecimal)),
+                   b"     64 first\n     6b second\n".to_vec());
+    }
+
+    #[test]
+    fn encodings() {
+        let encoding = |encoding| Options { encoding, ..Options::default() };
+
+        let eight = b"ab\0caf\xe9\0";
+        assert_eq!(strings_with(eight, encoding(Encoding::Ascii)), b"".to_vec());
+        assert_eq!(strings_with(eight, encoding(Encoding::EightBit)), b"caf\xe9\n".to_vec());
+
+        let le = b"h\0\xe9\0l\0l\0o\0\0\0";
+        assert_eq!(strings_with(le, encoding(Encoding::Utf16Le)),
+                   "h\u{e9}llo\n".as_bytes().to_vec());
+        // Read one byte in, the same bytes are big-endian.
+        assert_eq!(strings_with(le, encoding(Encoding::Utf16Be)),
+                   "\u{e9}llo\n".as_bytes().to_vec());
+        assert_eq!(strings_with(le, encoding(Encoding::Ascii)), b"".to_vec());
+
+        let be = b"\0\0\0w\0\0\0i\0\0\0d\0\0\0e\0\0\x01\0";
+        assert_eq!(strings_with(be, encoding(Encoding::Ucs4Be)), b"wide\n".to_vec());
+        assert_eq!(strings_with(be, encoding(Encoding::Ucs4Le)), b"".to_vec());
+
+        let le = b"w\0\0\0i\0\0\0d\0\0\0e\0\0\0\0\0\0\0";
+        assert_eq!(strings_with(le, encoding(Encoding::Ucs4Le)), b"wide\n".to_vec());
+    }
+
+    #[test]
+    fn unaligned() {
+        let options = Options {
+            radix: Some(Radix::Decimal),
+            encoding: Encoding::Utf16Le,
+            ..Options::default()
+        };
+        assert_eq!(strings_with(b"Xh\0e\0l\0l\0o\0\0\0", options), b"      1 hello\n".to_vec());
+
+        let options = Options { encoding: Encoding::Ucs4Be, ..options };
+        assert_eq!(strings_with(b"\0\0\0\0\0w\0\0\0i\0\0\0d\0\0\0e\0", options),
+                   b"      2 wide\n".to_vec());
+    }
+
+    #[test]
+    fn wide_offsets() {
+        let options = Options {
+            radix: Some(Radix::Decimal),
+            encoding: Encoding::Utf16Le,
+            ..Options::default()
+        };
+
+        assert_eq!(strings_with(b"\0\0a\0b\0c\0d\0\0\0", options), b"      2 abcd\n".to_vec());
+    }
+
+    #[test]
+    fn unicode() {
+        let unicode = |unicode| Options { unicode, ..Options::default() };
+        let input = "\0gr\u{fc}\u{df}e\0\u{1f600}abcd\0".as_bytes();
+
+        assert_eq!(strings_with(input, unicode(Unicode::Default)), b"abcd\n".to_vec());
+        assert_eq!(strings_with(input, unicode(Unicode::Invalid)), b"abcd\n".to_vec());
+        assert_eq!(strings_with(input, unicode(Unicode::Locale)),
+                   "gr\u{fc}\u{df}e\n\u{1f600}abcd\n".as_bytes().to_vec());
+        assert_eq!(strings_with(input, unicode(Unicode::Escape)),
+                   b"gr\\u00fc\\u00dfe\n\\U0001f600abcd\n".to_vec());
+        assert_eq!(strings_with(input, unicode(Unicode::Hex)),
+                   b"gr<0xc3bc><0xc39f>e\n<0xf09f9880>abcd\n".to_vec());
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let options = Options {
+            unicode: Unicode::Locale,
+            radix: Some(Radix::Decimal),
+            ..Options::default()
+        };
+
+        // A truncated sequence does not swallow the following printable bytes.
+        assert_eq!(strings_with(b"\xe2\x82abcd\0", options), b"      2 abcd\n".to_vec());
+        // Overlong encodings and control characters are not printable.
+        assert_eq!(strings_with(b"\xe0\x80\xafabcd\0", options), b"      3 abcd\n".to_vec());
+        assert_eq!(strings_with(b"\xc2\x85abcd\0", options), b"      2 abcd\n".to_vec());
+
+        let eight = Options { encoding: Encoding::EightBit, ..options };
+        assert_eq!(strings_with(b"\xe2\x82abcd\0", eight), b"      0 \xe2\x82abcd\n".to_vec());
+        let invalid = Options { unicode: Unicode::Invalid, ..eight };
+        assert_eq!(strings_with("\0ab\u{e9}cdef\0".as_bytes(), invalid), b"      5 cdef\n".to_vec());
+    }
+
+    #[test]
+    fn scanner() {
+        let options = Options { min: 3, encoding: Encoding::Utf16Le, ..Options::default() };
+        let input = b"a\0b\0\0\0\xe9\0t\0\xe9\0\0\0w\0i\0d\0e\0";
+        let found: Vec<_> = StringScanner::new(&input[..], options).map(|x| x.unwrap()).collect();
+
+        assert_eq!(found, vec![
+            ScannedString {
+                offset: 6,
+                encoding: Encoding::Utf16Le,
+                len: 3,
+                bytes: "\u{e9}t\u{e9}".as_bytes().to_vec(),
+            },
+            // The end of the stream terminates a string.
+            ScannedString {
+                offset: 14,
+                encoding: Encoding::Utf16Le,
+                len: 4,
+                bytes: b"wide".to_vec(),
+            },
+        ]);
+        assert_eq!(found[0].as_str(), Some("\u{e9}t\u{e9}"));
+    }
 }
@@ -0,0 +1,186 @@
+use std::cmp;
+use std::fmt::{Display, LowerExp};
+
+use convert::u8_to_oct;
+use strings::IsPrintable;
+
+/// The names of the ASCII control characters.
+const NAMES: [&str; 32] = [
+    "nul", "soh", "stx", "etx", "eot", "enq", "ack", "bel", "bs", "ht", "lf", "vt", "ff", "cr",
+    "so", "si", "dle", "dc1", "dc2", "dc3", "dc4", "nak", "syn", "etb", "can", "em", "sub", "esc",
+    "fs", "gs", "rs", "us",
+];
+
+/// The name of an ASCII control character, like `nul` or `del`.
+pub fn name(b: u8) -> Option<&'static str> {
+    match b {
+        0x7F => Some("del"),
+        b => NAMES.get(b as usize).cloned(),
+    }
+}
+
+/// Write a byte as a character, with C escapes or three octal digits for the non-printable ones.
+pub fn escape(b: u8, out: &mut Vec<u8>) {
+    let escape: &[u8] = match b {
+        0 => b"\\0",
+        7 => b"\\a",
+        8 => b"\\b",
+        b'\t' => b"\\t",
+        b'\n' => b"\\n",
+        0x0B => b"\\v",
+        0x0C => b"\\f",
+        b'\r' => b"\\r",
+        b if b.is_printable() => return out.push(b),
+        b => {
+            let (h, m, l) = u8_to_oct(b);
+            return out.extend_from_slice(&[b'0' + h, b'0' + m, b'0' + l]);
+        },
+    };
+    out.extend_from_slice(escape);
+}
+
+/// Write the body of a field, padded by spaces to `width`, on the right if `left` is set.
+pub fn pad(out: &mut Vec<u8>, left: bool, width: usize, body: &[u8]) {
+    let fill = width.saturating_sub(body.len());
+    if !left {
+        out.extend((0..fill).map(|_| b' '));
+    }
+    out.extend_from_slice(body);
+    if left {
+        out.extend((0..fill).map(|_| b' '));
+    }
+}
+
+/// Split a number in the exponential notation of Rust into its mantissa and exponent.
+fn split_exponent(e: &str) -> Option<(&str, i32)> {
+    e.find('e').map(|n| (&e[..n], e[n + 1..].parse().unwrap_or(0)))
+}
+
+/// Write a number in exponential notation like C, with the exponent signed and of at least two
+/// digits.
+fn with_exponent(mantissa: &str, exponent: i32) -> String {
+    format!("{}e{:+03}", mantissa, exponent)
+}
+
+/// Format a floating point number like `%g` of C, with the least precision of at least
+/// `precision` digits, which represents the number exactly.
+pub fn shortest<F: Display + LowerExp>(x: F, precision: i32) -> String {
+    let e = format!("{:e}", x);
+    let (mantissa, exponent) = match split_exponent(&e) {
+        Some(split) => split,
+        // Infinity and NaN.
+        None => return e.to_lowercase(),
+    };
+
+    let digits = mantissa.bytes().filter(|b| b.is_ascii_digit()).count() as i32;
+    if exponent < -4 || exponent >= cmp::max(digits, precision) {
+        with_exponent(mantissa, exponent)
+    } else {
+        format!("{}", x)
+    }
+}
+
+/// Format a floating point number like `printf`, without the sign.
+///
+/// The style is the conversion character, `e`, `f` or `g`, which is uppercase for an uppercase
+/// result, and `alternate` keeps the trailing zeros of the `g` style.
+pub fn printf(x: f64, style: u8, precision: usize, alternate: bool) -> String {
+    let upper = style.is_ascii_uppercase();
+    let exponential = |precision| {
+        let e = format!("{:.*e}", precision, x);
+        match split_exponent(&e) {
+            Some((mantissa, exponent)) => with_exponent(mantissa, exponent),
+            None => e,
+        }
+    };
+
+    let s = if x.is_nan() {
+        "nan".to_owned()
+    } else if x.is_infinite() {
+        "inf".to_owned()
+    } else {
+        match style.to_ascii_lowercase() {
+            b'e' => exponential(precision),
+            b'f' => format!("{:.*}", precision, x),
+            _ => {
+                // The shortest of the two styles, with the precision counting significant digits.
+                let precision = cmp::max(precision, 1);
+                let e = format!("{:.*e}", precision - 1, x);
+                let exponent = split_exponent(&e).map_or(0, |(_, exponent)| exponent);
+                let mut s = if exponent < -4 || exponent >= precision as i32 {
+                    exponential(precision - 1)
+                } else {
+                    format!("{:.*}", (precision as i32 - 1 - exponent) as usize, x)
+                };
+
+                if !alternate {
+                    // Trailing zeros are removed.
+                    let split = s.find('e').unwrap_or(s.len());
+                    let (mantissa, exponent) = s.split_at(split);
+                    let mantissa = if mantissa.contains('.') {
+                        mantissa.trim_end_matches('0').trim_end_matches('.')
+                    } else {
+                        mantissa
+                    };
+                    s = format!("{}{}", mantissa, exponent);
+                }
+                s
+            },
+        }
+    };
+
+    if upper {
+        s.to_uppercase()
+    } else {
+        s
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn names() {
+        assert_eq!(name(0), Some("nul"));
+        assert_eq!(name(b'\n'), Some("lf"));
+        assert_eq!(name(0x1F), Some("us"));
+        assert_eq!(name(0x7F), Some("del"));
+        assert_eq!(name(b' '), None);
+        assert_eq!(name(0x80), None);
+    }
+
+    #[test]
+    fn escapes() {
+        let mut out = Vec::new();
+        for &b in b"\0\x07\x08\t\n\x0b\x0c\ra~\x7f\xe8" {
+            escape(b, &mut out);
+            out.push(b' ');
+        }
+        assert_eq!(out, &b"\\0 \\a \\b \\t \\n \\v \\f \\r a ~ 177 350 "[..]);
+    }
+
+    #[test]
+    fn padding() {
+        let mut out = Vec::new();
+        pad(&mut out, false, 4, b"ab");
+        pad(&mut out, true, 4, b"cd");
+        pad(&mut out, false, 1, b"ef");
+        assert_eq!(out, &b"  abcd  ef"[..]);
+    }
+
+    #[test]
+    fn floats() {
+        assert_eq!(shortest(1.5f32, 6), "1.5");
+        assert_eq!(shortest(71278720f32, 6), "7.127872e+07");
+        assert_eq!(shortest(1e-5f64, 15), "1e-05");
+        assert_eq!(shortest(f64::NEG_INFINITY, 15), "-inf");
+
+        assert_eq!(printf(1.5, b'e', 2, false), "1.50e+00");
+        assert_eq!(printf(1.5, b'f', 3, false), "1.500");
+        assert_eq!(printf(1e-5, b'G', 6, false), "1E-05");
+        assert_eq!(printf(100.0, b'g', 6, false), "100");
+        assert_eq!(printf(100.0, b'g', 6, true), "100.000");
+        assert_eq!(printf(f64::NAN, b'E', 6, false), "NAN");
+    }
+}